@@ -0,0 +1,514 @@
+//! ACL admin requests. rust-rdkafka doesn't wrap librdkafka's CreateAcls, DescribeAcls and
+//! DeleteAcls, so they are called here on the admin client's native handle. Each request blocks
+//! until the brokers respond or the timeout passes, so callers run them with spawn_blocking.
+
+use std::ffi::{CStr, CString, c_char, c_int};
+use std::ptr;
+use std::time::Duration;
+
+use anyhow::{Context, anyhow, bail};
+use rdkafka::bindings as rdsys;
+use rdkafka::bindings::{rd_kafka_AdminOptions_t, rd_kafka_error_t, rd_kafka_event_t, rd_kafka_queue_t, rd_kafka_t};
+use rdkafka::types::{RDKafkaAdminOp, RDKafkaRespErr};
+use rdkafka_autoschematic_fork as rdkafka;
+
+use crate::client::KafkaAdminClient;
+use crate::resource::{
+    KafkaAcl, KafkaAclOperation, KafkaAclPermission, KafkaPrincipal, KafkaResourcePatternType, KafkaResourceType,
+};
+
+#[repr(C)]
+struct AclBinding {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct AclResult {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct DeleteAclsResponse {
+    _private: [u8; 0],
+}
+
+// librdkafka's enums are passed as plain ints, so values this librdkafka build doesn't know
+// about, such as the User resource type or the token operations on older builds, are rejected
+// by librdkafka with an error rather than being undefined behaviour here.
+unsafe extern "C" {
+    fn rd_kafka_AclBinding_new(
+        restype: c_int,
+        name: *const c_char,
+        resource_pattern_type: c_int,
+        principal: *const c_char,
+        host: *const c_char,
+        operation: c_int,
+        permission_type: c_int,
+        errstr: *mut c_char,
+        errstr_size: usize,
+    ) -> *mut AclBinding;
+    fn rd_kafka_AclBindingFilter_new(
+        restype: c_int,
+        name: *const c_char,
+        resource_pattern_type: c_int,
+        principal: *const c_char,
+        host: *const c_char,
+        operation: c_int,
+        permission_type: c_int,
+        errstr: *mut c_char,
+        errstr_size: usize,
+    ) -> *mut AclBinding;
+    fn rd_kafka_AclBinding_destroy(acl: *mut AclBinding);
+    fn rd_kafka_AclBinding_restype(acl: *const AclBinding) -> c_int;
+    fn rd_kafka_AclBinding_name(acl: *const AclBinding) -> *const c_char;
+    fn rd_kafka_AclBinding_resource_pattern_type(acl: *const AclBinding) -> c_int;
+    fn rd_kafka_AclBinding_principal(acl: *const AclBinding) -> *const c_char;
+    fn rd_kafka_AclBinding_host(acl: *const AclBinding) -> *const c_char;
+    fn rd_kafka_AclBinding_operation(acl: *const AclBinding) -> c_int;
+    fn rd_kafka_AclBinding_permission_type(acl: *const AclBinding) -> c_int;
+
+    fn rd_kafka_CreateAcls(
+        rk: *mut rd_kafka_t,
+        new_acls: *mut *mut AclBinding,
+        new_acls_cnt: usize,
+        options: *const rd_kafka_AdminOptions_t,
+        rkqu: *mut rd_kafka_queue_t,
+    );
+    fn rd_kafka_DescribeAcls(
+        rk: *mut rd_kafka_t,
+        acl_filter: *mut AclBinding,
+        options: *const rd_kafka_AdminOptions_t,
+        rkqu: *mut rd_kafka_queue_t,
+    );
+    fn rd_kafka_DeleteAcls(
+        rk: *mut rd_kafka_t,
+        del_acls: *mut *mut AclBinding,
+        del_acls_cnt: usize,
+        options: *const rd_kafka_AdminOptions_t,
+        rkqu: *mut rd_kafka_queue_t,
+    );
+
+    fn rd_kafka_event_CreateAcls_result(rkev: *mut rd_kafka_event_t) -> *const rd_kafka_event_t;
+    fn rd_kafka_event_DescribeAcls_result(rkev: *mut rd_kafka_event_t) -> *const rd_kafka_event_t;
+    fn rd_kafka_event_DeleteAcls_result(rkev: *mut rd_kafka_event_t) -> *const rd_kafka_event_t;
+
+    fn rd_kafka_CreateAcls_result_acls(result: *const rd_kafka_event_t, cntp: *mut usize) -> *const *const AclResult;
+    fn rd_kafka_acl_result_error(aclres: *const AclResult) -> *const rd_kafka_error_t;
+    fn rd_kafka_DescribeAcls_result_acls(result: *const rd_kafka_event_t, cntp: *mut usize) -> *const *const AclBinding;
+    fn rd_kafka_DeleteAcls_result_responses(
+        result: *const rd_kafka_event_t,
+        cntp: *mut usize,
+    ) -> *const *const DeleteAclsResponse;
+    fn rd_kafka_DeleteAcls_result_response_error(response: *const DeleteAclsResponse) -> *const rd_kafka_error_t;
+    fn rd_kafka_DeleteAcls_result_response_matching_acls(
+        response: *const DeleteAclsResponse,
+        cntp: *mut usize,
+    ) -> *const *const AclBinding;
+}
+
+// Wire values from librdkafka's rd_kafka_ResourceType_t, rd_kafka_ResourcePatternType_t,
+// rd_kafka_AclOperation_t and rd_kafka_AclPermissionType_t, which follow Kafka's own codes.
+// librdkafka's enums end before the delegation token codes (resource types 6 and 7, operations
+// 13 and 14), which KafkaAcl::validate rejects before they reach a request.
+const ANY: c_int = 1;
+
+fn resource_type_to_wire(resource_type: &KafkaResourceType) -> c_int {
+    match resource_type {
+        KafkaResourceType::Topic => 2,
+        KafkaResourceType::Group => 3,
+        KafkaResourceType::Cluster => 4,
+        KafkaResourceType::TransactionalId => 5,
+        KafkaResourceType::DelegationToken => 6,
+        KafkaResourceType::User => 7,
+    }
+}
+
+fn resource_type_from_wire(value: c_int) -> Option<KafkaResourceType> {
+    match value {
+        2 => Some(KafkaResourceType::Topic),
+        3 => Some(KafkaResourceType::Group),
+        4 => Some(KafkaResourceType::Cluster),
+        5 => Some(KafkaResourceType::TransactionalId),
+        6 => Some(KafkaResourceType::DelegationToken),
+        7 => Some(KafkaResourceType::User),
+        _ => None,
+    }
+}
+
+fn pattern_type_to_wire(pattern_type: &KafkaResourcePatternType) -> c_int {
+    match pattern_type {
+        KafkaResourcePatternType::Any => ANY,
+        KafkaResourcePatternType::Match => 2,
+        KafkaResourcePatternType::Literal => 3,
+        KafkaResourcePatternType::Prefixed => 4,
+    }
+}
+
+fn pattern_type_from_wire(value: c_int) -> Option<KafkaResourcePatternType> {
+    match value {
+        3 => Some(KafkaResourcePatternType::Literal),
+        4 => Some(KafkaResourcePatternType::Prefixed),
+        _ => None,
+    }
+}
+
+fn operation_to_wire(operation: &KafkaAclOperation) -> c_int {
+    match operation {
+        KafkaAclOperation::All => 2,
+        KafkaAclOperation::Read => 3,
+        KafkaAclOperation::Write => 4,
+        KafkaAclOperation::Create => 5,
+        KafkaAclOperation::Delete => 6,
+        KafkaAclOperation::Alter => 7,
+        KafkaAclOperation::Describe => 8,
+        KafkaAclOperation::ClusterAction => 9,
+        KafkaAclOperation::DescribeConfigs => 10,
+        KafkaAclOperation::AlterConfigs => 11,
+        KafkaAclOperation::IdempotentWrite => 12,
+        KafkaAclOperation::CreateTokens => 13,
+        KafkaAclOperation::DescribeTokens => 14,
+    }
+}
+
+fn operation_from_wire(value: c_int) -> Option<KafkaAclOperation> {
+    match value {
+        2 => Some(KafkaAclOperation::All),
+        3 => Some(KafkaAclOperation::Read),
+        4 => Some(KafkaAclOperation::Write),
+        5 => Some(KafkaAclOperation::Create),
+        6 => Some(KafkaAclOperation::Delete),
+        7 => Some(KafkaAclOperation::Alter),
+        8 => Some(KafkaAclOperation::Describe),
+        9 => Some(KafkaAclOperation::ClusterAction),
+        10 => Some(KafkaAclOperation::DescribeConfigs),
+        11 => Some(KafkaAclOperation::AlterConfigs),
+        12 => Some(KafkaAclOperation::IdempotentWrite),
+        13 => Some(KafkaAclOperation::CreateTokens),
+        14 => Some(KafkaAclOperation::DescribeTokens),
+        _ => None,
+    }
+}
+
+fn permission_to_wire(permission: &KafkaAclPermission) -> c_int {
+    match permission {
+        KafkaAclPermission::Deny => 2,
+        KafkaAclPermission::Allow => 3,
+    }
+}
+
+fn permission_from_wire(value: c_int) -> Option<KafkaAclPermission> {
+    match value {
+        2 => Some(KafkaAclPermission::Deny),
+        3 => Some(KafkaAclPermission::Allow),
+        _ => None,
+    }
+}
+
+/// Selects ACL bindings to describe. Fields left as None match any value, and `pattern_type`
+/// may also be `Match` to find every binding that applies to `resource_name`.
+#[derive(Debug, Clone, Default)]
+pub struct KafkaAclFilter {
+    pub resource_type: Option<KafkaResourceType>,
+    pub resource_name: Option<String>,
+    pub pattern_type: Option<KafkaResourcePatternType>,
+    pub principal: Option<KafkaPrincipal>,
+    pub host: Option<String>,
+    pub operation: Option<KafkaAclOperation>,
+    pub permission: Option<KafkaAclPermission>,
+}
+
+impl KafkaAclFilter {
+    /// Every binding for one principal
+    pub fn principal(principal: &KafkaPrincipal) -> Self {
+        Self {
+            principal: Some(principal.clone()),
+            ..Default::default()
+        }
+    }
+}
+
+/// Owns the bindings or filters handed to a request
+struct NativeBindings(Vec<*mut AclBinding>);
+
+impl Drop for NativeBindings {
+    fn drop(&mut self) {
+        for binding in &self.0 {
+            unsafe { rd_kafka_AclBinding_destroy(*binding) };
+        }
+    }
+}
+
+pub(crate) struct NativeEvent(pub(crate) *mut rd_kafka_event_t);
+
+impl Drop for NativeEvent {
+    fn drop(&mut self) {
+        unsafe { rdsys::rd_kafka_event_destroy(self.0) };
+    }
+}
+
+pub(crate) fn cstring(s: &str) -> anyhow::Result<CString> {
+    CString::new(s).with_context(|| format!("'{}' contains a NUL byte", s))
+}
+
+fn optional_ptr(s: &Option<CString>) -> *const c_char {
+    s.as_ref().map(|s| s.as_ptr()).unwrap_or(ptr::null())
+}
+
+pub(crate) unsafe fn owned_str(s: *const c_char) -> String {
+    if s.is_null() {
+        String::new()
+    } else {
+        unsafe { CStr::from_ptr(s) }.to_string_lossy().into_owned()
+    }
+}
+
+pub(crate) unsafe fn error_message(error: *const rd_kafka_error_t) -> Option<String> {
+    if error.is_null() {
+        None
+    } else {
+        Some(unsafe { owned_str(rdsys::rd_kafka_error_string(error)) })
+    }
+}
+
+fn new_binding(acl: &KafkaAcl, filter: bool) -> anyhow::Result<*mut AclBinding> {
+    let name = cstring(&acl.resource_name)?;
+    let principal = cstring(&acl.principal.to_wire())?;
+    let host = cstring(&acl.host)?;
+    let mut errstr = [0 as c_char; 512];
+
+    let new = if filter {
+        rd_kafka_AclBindingFilter_new
+    } else {
+        rd_kafka_AclBinding_new
+    };
+
+    acl.validate()?;
+
+    let binding = unsafe {
+        new(
+            resource_type_to_wire(&acl.resource_type),
+            name.as_ptr(),
+            pattern_type_to_wire(&acl.pattern_type),
+            principal.as_ptr(),
+            host.as_ptr(),
+            operation_to_wire(&acl.operation),
+            permission_to_wire(&acl.permission),
+            errstr.as_mut_ptr(),
+            errstr.len(),
+        )
+    };
+
+    if binding.is_null() {
+        let message = unsafe { owned_str(errstr.as_ptr()) };
+        bail!("Invalid ACL binding {}: {}", acl.describe(), message);
+    }
+    Ok(binding)
+}
+
+fn new_filter(filter: &KafkaAclFilter) -> anyhow::Result<*mut AclBinding> {
+    let name = filter.resource_name.as_deref().map(cstring).transpose()?;
+    let principal = filter.principal.as_ref().map(|p| cstring(&p.to_wire())).transpose()?;
+    let host = filter.host.as_deref().map(cstring).transpose()?;
+    let mut errstr = [0 as c_char; 512];
+
+    let binding = unsafe {
+        rd_kafka_AclBindingFilter_new(
+            filter.resource_type.as_ref().map(resource_type_to_wire).unwrap_or(ANY),
+            optional_ptr(&name),
+            filter.pattern_type.as_ref().map(pattern_type_to_wire).unwrap_or(ANY),
+            optional_ptr(&principal),
+            optional_ptr(&host),
+            filter.operation.as_ref().map(operation_to_wire).unwrap_or(ANY),
+            filter.permission.as_ref().map(permission_to_wire).unwrap_or(ANY),
+            errstr.as_mut_ptr(),
+            errstr.len(),
+        )
+    };
+
+    if binding.is_null() {
+        let message = unsafe { owned_str(errstr.as_ptr()) };
+        bail!("Invalid ACL filter: {}", message);
+    }
+    Ok(binding)
+}
+
+/// Read a binding returned by the brokers. Returns None for bindings this connector can't
+/// represent, such as unknown resource types.
+unsafe fn read_binding(binding: *const AclBinding) -> Option<KafkaAcl> {
+    unsafe {
+        Some(KafkaAcl {
+            resource_type: resource_type_from_wire(rd_kafka_AclBinding_restype(binding))?,
+            resource_name: owned_str(rd_kafka_AclBinding_name(binding)),
+            pattern_type: pattern_type_from_wire(rd_kafka_AclBinding_resource_pattern_type(binding))?,
+            principal: KafkaPrincipal::from_wire(&owned_str(rd_kafka_AclBinding_principal(binding))).ok()?,
+            host: owned_str(rd_kafka_AclBinding_host(binding)),
+            operation: operation_from_wire(rd_kafka_AclBinding_operation(binding))?,
+            permission: permission_from_wire(rd_kafka_AclBinding_permission_type(binding))?,
+        })
+    }
+}
+
+/// Send an admin request on a temporary queue and wait for its result event
+pub(crate) fn request(
+    client: &KafkaAdminClient,
+    timeout: Duration,
+    send: impl FnOnce(*mut rd_kafka_t, *const rd_kafka_AdminOptions_t, *mut rd_kafka_queue_t),
+) -> anyhow::Result<NativeEvent> {
+    let rk = client.inner().native_ptr();
+    let mut errstr = [0 as c_char; 512];
+
+    let event = unsafe {
+        let options = rdsys::rd_kafka_AdminOptions_new(rk, RDKafkaAdminOp::RD_KAFKA_ADMIN_OP_ANY);
+        rdsys::rd_kafka_AdminOptions_set_request_timeout(
+            options,
+            timeout.as_millis() as c_int,
+            errstr.as_mut_ptr(),
+            errstr.len(),
+        );
+        let queue = rdsys::rd_kafka_queue_new(rk);
+
+        send(rk, options, queue);
+        // The request times out on its own, so the extra second only covers delivering the result
+        let event = rdsys::rd_kafka_queue_poll(queue, (timeout + Duration::from_secs(1)).as_millis() as c_int);
+
+        rdsys::rd_kafka_queue_destroy(queue);
+        rdsys::rd_kafka_AdminOptions_destroy(options);
+        event
+    };
+
+    if event.is_null() {
+        bail!("Timed out waiting for the brokers to answer an admin request");
+    }
+    let event = NativeEvent(event);
+
+    if unsafe { rdsys::rd_kafka_event_error(event.0) } != RDKafkaRespErr::RD_KAFKA_RESP_ERR_NO_ERROR {
+        let message = unsafe { owned_str(rdsys::rd_kafka_event_error_string(event.0)) };
+        bail!("Admin request failed: {}", message);
+    }
+
+    Ok(event)
+}
+
+/// Every ACL binding matching a filter. Bindings that can't be represented are skipped
+/// with a warning.
+pub fn describe_acls(
+    client: &KafkaAdminClient,
+    filter: &KafkaAclFilter,
+    timeout: Duration,
+) -> anyhow::Result<Vec<KafkaAcl>> {
+    let filter = NativeBindings(vec![new_filter(filter)?]);
+
+    let event = request(client, timeout, |rk, options, queue| unsafe {
+        rd_kafka_DescribeAcls(rk, filter.0[0], options, queue)
+    })?;
+
+    let mut acls = Vec::new();
+    unsafe {
+        let result = rd_kafka_event_DescribeAcls_result(event.0);
+        if result.is_null() {
+            bail!("Unexpected result event for DescribeAcls");
+        }
+
+        let mut count = 0;
+        let bindings = rd_kafka_DescribeAcls_result_acls(result, &mut count);
+        for i in 0..count {
+            let binding = *bindings.add(i);
+            match read_binding(binding) {
+                Some(acl) => acls.push(acl),
+                None => tracing::warn!(
+                    "Skipping ACL binding for '{}' on '{}' that this connector can't represent",
+                    owned_str(rd_kafka_AclBinding_principal(binding)),
+                    owned_str(rd_kafka_AclBinding_name(binding))
+                ),
+            }
+        }
+    }
+
+    Ok(acls)
+}
+
+/// Create ACL bindings. Creating a binding that already exists succeeds.
+pub fn create_acls(client: &KafkaAdminClient, acls: &[KafkaAcl], timeout: Duration) -> anyhow::Result<()> {
+    if acls.is_empty() {
+        return Ok(());
+    }
+
+    let mut bindings = NativeBindings(Vec::with_capacity(acls.len()));
+    for acl in acls {
+        bindings.0.push(new_binding(acl, false)?);
+    }
+
+    let event = request(client, timeout, |rk, options, queue| unsafe {
+        rd_kafka_CreateAcls(rk, bindings.0.as_mut_ptr(), bindings.0.len(), options, queue)
+    })?;
+
+    unsafe {
+        let result = rd_kafka_event_CreateAcls_result(event.0);
+        if result.is_null() {
+            bail!("Unexpected result event for CreateAcls");
+        }
+
+        let mut count = 0;
+        let results = rd_kafka_CreateAcls_result_acls(result, &mut count);
+        let errors: Vec<String> = (0..count)
+            .filter_map(|i| {
+                let message = error_message(rd_kafka_acl_result_error(*results.add(i)))?;
+                let acl = acls.get(i).map(KafkaAcl::describe).unwrap_or_default();
+                Some(format!("{}: {}", acl, message))
+            })
+            .collect();
+
+        if !errors.is_empty() {
+            return Err(anyhow!("Failed to create ACLs: {}", errors.join("; ")));
+        }
+    }
+
+    Ok(())
+}
+
+/// Delete ACL bindings, each matched exactly so that no other binding is removed.
+/// Returns the number of bindings deleted.
+pub fn delete_acls(client: &KafkaAdminClient, acls: &[KafkaAcl], timeout: Duration) -> anyhow::Result<usize> {
+    if acls.is_empty() {
+        return Ok(0);
+    }
+
+    let mut filters = NativeBindings(Vec::with_capacity(acls.len()));
+    for acl in acls {
+        filters.0.push(new_binding(acl, true)?);
+    }
+
+    let event = request(client, timeout, |rk, options, queue| unsafe {
+        rd_kafka_DeleteAcls(rk, filters.0.as_mut_ptr(), filters.0.len(), options, queue)
+    })?;
+
+    let mut deleted = 0;
+    unsafe {
+        let result = rd_kafka_event_DeleteAcls_result(event.0);
+        if result.is_null() {
+            bail!("Unexpected result event for DeleteAcls");
+        }
+
+        let mut count = 0;
+        let responses = rd_kafka_DeleteAcls_result_responses(result, &mut count);
+        let mut errors = Vec::new();
+        for i in 0..count {
+            let response = *responses.add(i);
+            if let Some(message) = error_message(rd_kafka_DeleteAcls_result_response_error(response)) {
+                let acl = acls.get(i).map(KafkaAcl::describe).unwrap_or_default();
+                errors.push(format!("{}: {}", acl, message));
+                continue;
+            }
+
+            let mut matched = 0;
+            rd_kafka_DeleteAcls_result_response_matching_acls(response, &mut matched);
+            deleted += matched;
+        }
+
+        if !errors.is_empty() {
+            bail!("Failed to delete ACLs: {}", errors.join("; "));
+        }
+    }
+
+    Ok(deleted)
+}
@@ -0,0 +1,66 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use anyhow::Context;
+use autoschematic_core::{
+    connector::{OutputMapExec, OutputMapFile, ResourceAddress},
+    util::RON,
+};
+
+use crate::{addr::KafkaResourceAddress, resource::KafkaAcl};
+
+// The bindings an ACL file has created are kept in its outputs, which outlive the file, so
+// bindings removed from a file, or whose file is deleted, are still known to belong to it and
+// can be deleted.
+
+const BINDINGS: &str = "bindings";
+
+/// The bindings recorded in an ACL file's outputs
+pub fn owned(prefix: &Path, addr: &KafkaResourceAddress) -> anyhow::Result<BTreeSet<KafkaAcl>> {
+    match addr.get_output(prefix, BINDINGS)? {
+        Some(bindings) => RON
+            .from_str(&bindings)
+            .with_context(|| format!("Failed to parse the bindings recorded for {}", addr.to_path_buf().display())),
+        None => Ok(BTreeSet::new()),
+    }
+}
+
+/// The outputs recording that an ACL file created or deleted a binding. The set is written even
+/// once empty, since removing a resource's last output doesn't remove its output file.
+pub fn outputs(prefix: &Path, addr: &KafkaResourceAddress, acl: &KafkaAcl, created: bool) -> anyhow::Result<OutputMapExec> {
+    let mut bindings = owned(prefix, addr)?;
+    if created {
+        bindings.insert(acl.clone());
+    } else {
+        bindings.remove(acl);
+    }
+
+    Ok(OutputMapExec::from([(BINDINGS.to_string(), Some(RON.to_string(&bindings)?))]))
+}
+
+/// The ACL files in a cluster with recorded bindings, including those since deleted
+pub fn recorded_files(prefix: &Path, cluster: &str) -> anyhow::Result<Vec<String>> {
+    let addr = KafkaResourceAddress::Acl {
+        cluster: cluster.to_string(),
+        acl_id: String::new(),
+    };
+    let Some(dir) = OutputMapFile::path(prefix, &addr.to_path_buf())
+        .parent()
+        .map(Path::to_path_buf)
+    else {
+        return Ok(Vec::new());
+    };
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut acl_ids = Vec::new();
+    for entry in std::fs::read_dir(&dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let name = entry?.file_name();
+        if let Some(acl_id) = name.to_str().and_then(|name| name.strip_suffix(".ron.out.ron")) {
+            acl_ids.push(acl_id.to_string());
+        }
+    }
+
+    Ok(acl_ids)
+}
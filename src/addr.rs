@@ -4,6 +4,10 @@ use autoschematic_core::{connector::ResourceAddress, error_util::invalid_addr_pa
 
 use crate::task::KafkaTask;
 
+/// Directory under kafka/ holding definitions shared across clusters, in place of a cluster name.
+/// A cluster can't be named `_shared`.
+pub const SHARED_DIR: &str = "_shared";
+
 #[derive(Debug, Clone)]
 pub enum KafkaResourceAddress {
     /// Config file at kafka/config.ron
@@ -13,31 +17,70 @@ pub enum KafkaResourceAddress {
         cluster: String,
         topic: String,
     },
+    /// Topic applied to several clusters, at kafka/_shared/topics/{topic_name}.ron
+    SharedTopic {
+        topic: String,
+    },
     /// ACL at kafka/{cluster}/acls/{acl_id}.ron
     Acl {
         cluster: String,
         acl_id: String,
     },
+    /// Access for one principal at kafka/{cluster}/access/{principal}.ron, where users are
+    /// named alone (`alice.ron`) and other principals in full (`Group:admins.ron`)
+    Access {
+        cluster: String,
+        principal: String,
+    },
     /// Quota at kafka/{cluster}/quotas/{quota_id}.ron
     Quota {
         cluster: String,
         quota_id: String,
     },
+    /// Topic profile at kafka/{scope}/profiles/{name}.ron, where the scope is a cluster or
+    /// `_shared`. Profiles only exist in the repository; they're addressed so that diag can
+    /// check them and name the topic files that use them.
+    Profile {
+        scope: String,
+        name: String,
+    },
+    /// Task at kafka/tasks/{task_name}.ron
     Task {
         kind: KafkaTask,
     },
 }
 
+impl KafkaResourceAddress {
+    /// The cluster this address belongs to, if any
+    pub fn cluster(&self) -> Option<&str> {
+        match self {
+            KafkaResourceAddress::Topic { cluster, .. }
+            | KafkaResourceAddress::Acl { cluster, .. }
+            | KafkaResourceAddress::Access { cluster, .. }
+            | KafkaResourceAddress::Quota { cluster, .. } => Some(cluster),
+            KafkaResourceAddress::Config
+            | KafkaResourceAddress::SharedTopic { .. }
+            | KafkaResourceAddress::Profile { .. }
+            | KafkaResourceAddress::Task { .. } => None,
+        }
+    }
+}
+
 impl ResourceAddress for KafkaResourceAddress {
     fn to_path_buf(&self) -> PathBuf {
         match &self {
             KafkaResourceAddress::Config => PathBuf::from("kafka/config.ron"),
             KafkaResourceAddress::Topic { cluster, topic } => PathBuf::from(format!("kafka/{cluster}/topics/{topic}.ron")),
+            KafkaResourceAddress::SharedTopic { topic } => PathBuf::from(format!("kafka/{SHARED_DIR}/topics/{topic}.ron")),
             KafkaResourceAddress::Acl { cluster, acl_id } => PathBuf::from(format!("kafka/{cluster}/acls/{acl_id}.ron")),
+            KafkaResourceAddress::Access { cluster, principal } => {
+                PathBuf::from(format!("kafka/{cluster}/access/{principal}.ron"))
+            }
             KafkaResourceAddress::Quota { cluster, quota_id } => {
                 PathBuf::from(format!("kafka/{cluster}/quotas/{quota_id}.ron"))
             }
-            KafkaResourceAddress::Task { .. } => PathBuf::from(format!("kafka/task.ron")),
+            KafkaResourceAddress::Profile { scope, name } => PathBuf::from(format!("kafka/{scope}/profiles/{name}.ron")),
+            KafkaResourceAddress::Task { kind } => PathBuf::from(format!("kafka/tasks/{}.ron", kind.name())),
         }
     }
 
@@ -46,6 +89,21 @@ impl ResourceAddress for KafkaResourceAddress {
 
         match path_components[..] {
             ["kafka", "config.ron"] => Ok(KafkaResourceAddress::Config),
+            ["kafka", "tasks", task_file] if task_file.ends_with(".ron") => {
+                let task_name = task_file.strip_suffix(".ron").unwrap_or(task_file);
+
+                match KafkaTask::from_name(task_name) {
+                    Some(kind) => Ok(KafkaResourceAddress::Task { kind }),
+                    None => Err(invalid_addr_path(path)),
+                }
+            }
+            ["kafka", SHARED_DIR, "topics", topic_file] if topic_file.ends_with(".ron") => {
+                let topic = topic_file.strip_suffix(".ron").unwrap_or(topic_file);
+
+                Ok(KafkaResourceAddress::SharedTopic {
+                    topic: topic.to_string(),
+                })
+            }
             ["kafka", cluster, "topics", topic_file] if topic_file.ends_with(".ron") => {
                 let topic = topic_file.strip_suffix(".ron").unwrap_or(topic_file);
 
@@ -62,6 +120,14 @@ impl ResourceAddress for KafkaResourceAddress {
                     acl_id: acl_id.to_string(),
                 })
             }
+            ["kafka", cluster, "access", access_file] if access_file.ends_with(".ron") => {
+                let principal = access_file.strip_suffix(".ron").unwrap_or(access_file);
+
+                Ok(KafkaResourceAddress::Access {
+                    cluster: cluster.to_string(),
+                    principal: principal.to_string(),
+                })
+            }
             ["kafka", cluster, "quotas", quota_file] if quota_file.ends_with(".ron") => {
                 let quota_id = quota_file.strip_suffix(".ron").unwrap_or(quota_file);
                 Ok(KafkaResourceAddress::Quota {
@@ -69,6 +135,13 @@ impl ResourceAddress for KafkaResourceAddress {
                     quota_id: quota_id.to_string(),
                 })
             }
+            ["kafka", scope, "profiles", profile_file] if profile_file.ends_with(".ron") => {
+                let name = profile_file.strip_suffix(".ron").unwrap_or(profile_file);
+                Ok(KafkaResourceAddress::Profile {
+                    scope: scope.to_string(),
+                    name: name.to_string(),
+                })
+            }
             _ => Err(invalid_addr_path(path)),
         }
     }
@@ -0,0 +1,259 @@
+//! Topic archives, written before a topic is deleted and read back by the restore task.
+//!
+//! An archive is a directory at `{archive_dir}/{cluster}/{topic}/{archived_at_ms}/` holding:
+//!
+//! - `manifest.json`: a [`KafkaArchiveManifest`], with the topic's definition and the
+//!   watermarks and record count of every partition.
+//! - `partition-{n}.jsonl`: one [`KafkaArchivedRecord`] per line, in offset order. Keys,
+//!   values and header values are base64 (standard alphabet, padded), or null when absent.
+//!
+//! Archives are written to a `.partial` directory and renamed into place once every
+//! partition has been read, so an archive directory without that suffix is complete.
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, bail};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use rdkafka::{
+    Offset, TopicPartitionList,
+    consumer::Consumer,
+    error::KafkaError,
+    message::{Header, Headers, Message, OwnedHeaders},
+};
+use rdkafka_autoschematic_fork as rdkafka;
+use serde::{Deserialize, Serialize};
+
+use crate::{client::KafkaConsumer, resource::KafkaTopic};
+
+pub const ARCHIVE_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KafkaArchiveManifest {
+    pub format_version: u32,
+    pub cluster: String,
+    pub topic: String,
+    pub archived_at_ms: i64,
+    /// The topic's definition when it was archived, used to recreate it on restore
+    pub definition: KafkaTopic,
+    pub partitions: Vec<KafkaArchivedPartition>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KafkaArchivedPartition {
+    pub partition: i32,
+    pub low_watermark: i64,
+    pub high_watermark: i64,
+    /// Records written, which is fewer than the offset range on compacted or transactional topics
+    pub records: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KafkaArchivedRecord {
+    pub partition: i32,
+    pub offset: i64,
+    /// Milliseconds since the epoch, if the record had a timestamp
+    pub timestamp: Option<i64>,
+    pub key: Option<String>,
+    pub value: Option<String>,
+    #[serde(default)]
+    pub headers: Vec<KafkaArchivedHeader>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KafkaArchivedHeader {
+    pub key: String,
+    pub value: Option<String>,
+}
+
+impl KafkaArchivedRecord {
+    pub fn key_bytes(&self) -> anyhow::Result<Option<Vec<u8>>> {
+        decode(self.key.as_deref())
+    }
+
+    pub fn value_bytes(&self) -> anyhow::Result<Option<Vec<u8>>> {
+        decode(self.value.as_deref())
+    }
+
+    /// The record's headers, decoded for producing
+    pub fn owned_headers(&self) -> anyhow::Result<OwnedHeaders> {
+        let mut headers = OwnedHeaders::new_with_capacity(self.headers.len());
+        for header in &self.headers {
+            let value = decode(header.value.as_deref())?;
+            headers = headers.insert(Header {
+                key: &header.key,
+                value: value.as_deref(),
+            });
+        }
+        Ok(headers)
+    }
+}
+
+fn encode(bytes: Option<&[u8]>) -> Option<String> {
+    bytes.map(|b| STANDARD.encode(b))
+}
+
+fn decode(s: Option<&str>) -> anyhow::Result<Option<Vec<u8>>> {
+    Ok(match s {
+        Some(s) => Some(STANDARD.decode(s).context("Invalid base64 in archived record")?),
+        None => None,
+    })
+}
+
+fn partition_file_name(partition: i32) -> String {
+    format!("partition-{}.jsonl", partition)
+}
+
+/// Read every partition of a topic from its low to its high watermark into a new archive
+/// under `archive_dir`, returning the archive's path. Blocks on librdkafka.
+pub fn write_archive(
+    consumer: &KafkaConsumer,
+    cluster: &str,
+    topic: &str,
+    definition: KafkaTopic,
+    archive_dir: &Path,
+    timeout: Duration,
+) -> anyhow::Result<(PathBuf, KafkaArchiveManifest)> {
+    let archived_at_ms = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as i64;
+
+    let final_path = archive_dir.join(cluster).join(topic).join(archived_at_ms.to_string());
+    let partial_path = final_path.with_extension("partial");
+    std::fs::create_dir_all(&partial_path).with_context(|| format!("Failed to create {}", partial_path.display()))?;
+
+    let metadata = consumer.fetch_metadata(Some(topic), timeout)?;
+    let Some(topic_metadata) = metadata.topics().iter().find(|t| t.name() == topic) else {
+        bail!("Topic '{}' not found in cluster '{}'", topic, cluster);
+    };
+
+    let mut partition_ids: Vec<i32> = topic_metadata.partitions().iter().map(|p| p.id()).collect();
+    partition_ids.sort();
+
+    let mut partitions = Vec::new();
+    for partition in partition_ids {
+        let path = partial_path.join(partition_file_name(partition));
+        let file = File::create(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+
+        let (low_watermark, high_watermark) = consumer.fetch_watermarks(topic, partition, timeout)?;
+        let mut records = 0;
+
+        if high_watermark > low_watermark {
+            let mut tpl = TopicPartitionList::new();
+            tpl.add_partition_offset(topic, partition, Offset::Offset(low_watermark))?;
+            consumer.assign(&tpl)?;
+
+            loop {
+                let message = match consumer.poll(timeout) {
+                    None => bail!(
+                        "Timed out reading partition {} of topic '{}' at record {} of {}",
+                        partition,
+                        topic,
+                        records,
+                        high_watermark - low_watermark
+                    ),
+                    Some(Err(KafkaError::PartitionEOF(_))) => break,
+                    Some(Err(e)) => return Err(e).with_context(|| format!("Failed to read topic '{}'", topic)),
+                    Some(Ok(message)) => message,
+                };
+
+                // Records produced after the watermarks were taken are left out
+                if message.offset() >= high_watermark {
+                    break;
+                }
+
+                let record = KafkaArchivedRecord {
+                    partition,
+                    offset: message.offset(),
+                    timestamp: message.timestamp().to_millis(),
+                    key: encode(message.key()),
+                    value: encode(message.payload()),
+                    headers: message
+                        .headers()
+                        .map(|headers| {
+                            headers
+                                .iter()
+                                .map(|h| KafkaArchivedHeader {
+                                    key: h.key.to_string(),
+                                    value: encode(h.value),
+                                })
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                };
+
+                serde_json::to_writer(&mut writer, &record)?;
+                writer.write_all(b"\n")?;
+                records += 1;
+
+                if message.offset() >= high_watermark - 1 {
+                    break;
+                }
+            }
+
+            consumer.unassign()?;
+        }
+
+        writer.flush()?;
+        partitions.push(KafkaArchivedPartition {
+            partition,
+            low_watermark,
+            high_watermark,
+            records,
+        });
+    }
+
+    let manifest = KafkaArchiveManifest {
+        format_version: ARCHIVE_FORMAT_VERSION,
+        cluster: cluster.to_string(),
+        topic: topic.to_string(),
+        archived_at_ms,
+        definition,
+        partitions,
+    };
+
+    let manifest_path = partial_path.join("manifest.json");
+    std::fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)
+        .with_context(|| format!("Failed to write {}", manifest_path.display()))?;
+
+    std::fs::rename(&partial_path, &final_path)
+        .with_context(|| format!("Failed to move archive into place at {}", final_path.display()))?;
+
+    Ok((final_path, manifest))
+}
+
+/// Read the manifest of a complete archive
+pub fn read_manifest(archive_path: &Path) -> anyhow::Result<KafkaArchiveManifest> {
+    let path = archive_path.join("manifest.json");
+    let s = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let manifest: KafkaArchiveManifest =
+        serde_json::from_str(&s).with_context(|| format!("Failed to parse {}", path.display()))?;
+
+    if manifest.format_version != ARCHIVE_FORMAT_VERSION {
+        bail!(
+            "Unsupported archive format version {} in {} (expected {})",
+            manifest.format_version,
+            path.display(),
+            ARCHIVE_FORMAT_VERSION
+        );
+    }
+
+    Ok(manifest)
+}
+
+/// Iterate over the archived records of one partition, in offset order
+pub fn read_partition(
+    archive_path: &Path,
+    partition: i32,
+) -> anyhow::Result<impl Iterator<Item = anyhow::Result<KafkaArchivedRecord>>> {
+    let path = archive_path.join(partition_file_name(partition));
+    let file = File::open(&path).with_context(|| format!("Failed to open {}", path.display()))?;
+
+    Ok(BufReader::new(file).lines().enumerate().map(move |(i, line)| {
+        let line = line?;
+        serde_json::from_str(&line).with_context(|| format!("Invalid record at {}:{}", path.display(), i + 1))
+    }))
+}
@@ -1,20 +1,99 @@
 use crate::config::{KafkaAuth, KafkaClusterConfig};
-use anyhow::Context;
+use anyhow::{Context, bail};
 use rdkafka_autoschematic_fork as rdkafka;
-use rdkafka::admin::AdminClient;
-use rdkafka::client::DefaultClientContext;
+use rdkafka::admin::{AdminClient, AdminOptions, ConfigEntry, ConfigResource, OwnedResourceSpecifier, ResourceSpecifier};
+use rdkafka::client::ClientContext;
 use rdkafka::config::ClientConfig;
-use std::time::Duration;
+use rdkafka::consumer::{BaseConsumer, ConsumerContext};
+use rdkafka::producer::FutureProducer;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-pub type KafkaAdminClient = AdminClient<DefaultClientContext>;
+pub type KafkaAdminClient = AdminClient<KafkaClientContext>;
+pub type KafkaConsumer = BaseConsumer<KafkaClientContext>;
+pub type KafkaProducer = FutureProducer<KafkaClientContext>;
+
+/// Health of a configured cluster's admin client. Clusters without an entry have not
+/// been connected to yet.
+pub enum KafkaClusterState {
+    /// The client was created and passed identity checks
+    Ready(Arc<KafkaAdminClient>),
+    /// Creating or verifying the client failed at `at`; retried after `CONNECT_RETRY_BACKOFF`
+    Failed { error: String, at: Instant },
+}
+
+/// How long a cluster that failed to connect is reported as unavailable before the next attempt
+pub const CONNECT_RETRY_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Client context shared by every client for a cluster. OAUTHBEARER tokens are fetched by
+/// librdkafka's built-in OIDC support rather than a token refresh callback, since the admin
+/// client's poll thread never dispatches token refresh events.
+#[derive(Default)]
+pub struct KafkaClientContext;
+
+impl ConsumerContext for KafkaClientContext {}
+
+impl ClientContext for KafkaClientContext {}
 
 /// Create a Kafka admin client from cluster configuration
-pub fn create_admin_client(cluster_config: &KafkaClusterConfig) -> anyhow::Result<KafkaAdminClient> {
+pub fn create_admin_client(prefix: &Path, cluster_config: &KafkaClusterConfig) -> anyhow::Result<KafkaAdminClient> {
+    let (config, context) = client_config(prefix, cluster_config)?;
+
+    // Create admin client
+    config
+        .create_with_context(context)
+        .context("Failed to create Kafka admin client")
+}
+
+/// Create a Kafka consumer from cluster configuration. The consumer never subscribes;
+/// it is used for offset and group lookups, and for reading topic data directly.
+pub fn create_consumer(
+    prefix: &Path,
+    cluster_config: &KafkaClusterConfig,
+    group_id: Option<&str>,
+) -> anyhow::Result<KafkaConsumer> {
+    let (mut config, context) = client_config(prefix, cluster_config)?;
+
+    config.set("enable.auto.commit", "false");
+    config.set("enable.partition.eof", "true");
+    if let Some(group_id) = group_id {
+        config.set("group.id", group_id);
+    }
+
+    config.create_with_context(context).context("Failed to create Kafka consumer")
+}
+
+/// Create a Kafka producer from cluster configuration, used to restore archived topics.
+/// Idempotence keeps retries from duplicating or reordering records within a partition.
+pub fn create_producer(prefix: &Path, cluster_config: &KafkaClusterConfig) -> anyhow::Result<KafkaProducer> {
+    let (mut config, context) = client_config(prefix, cluster_config)?;
+
+    config.set("enable.idempotence", "true");
+
+    config.create_with_context(context).context("Failed to create Kafka producer")
+}
+
+/// Build the librdkafka client configuration shared by every client for a cluster
+fn client_config(prefix: &Path, cluster_config: &KafkaClusterConfig) -> anyhow::Result<(ClientConfig, KafkaClientContext)> {
     let mut config = ClientConfig::new();
+    let context = KafkaClientContext;
 
     config.set("bootstrap.servers", &cluster_config.bootstrap_servers);
 
     // Set authentication based on config
+    if let KafkaAuth::SaslPlain { password, .. }
+    | KafkaAuth::SaslScramSha256 { password, .. }
+    | KafkaAuth::SaslScramSha512 { password, .. } = &cluster_config.auth
+        && password.is_plaintext()
+    {
+        tracing::warn!(
+            "Plaintext password configured for brokers '{}'; prefer a secret://, env: or file: secret reference",
+            cluster_config.bootstrap_servers
+        );
+    }
+
     match &cluster_config.auth {
         KafkaAuth::None => {
             // No additional auth config needed
@@ -23,19 +102,19 @@ pub fn create_admin_client(cluster_config: &KafkaClusterConfig) -> anyhow::Resul
             config.set("security.protocol", "SASL_PLAINTEXT");
             config.set("sasl.mechanism", "PLAIN");
             config.set("sasl.username", username);
-            config.set("sasl.password", password);
+            config.set("sasl.password", password.resolve(prefix)?);
         }
         KafkaAuth::SaslScramSha256 { username, password } => {
             config.set("security.protocol", "SASL_PLAINTEXT");
             config.set("sasl.mechanism", "SCRAM-SHA-256");
             config.set("sasl.username", username);
-            config.set("sasl.password", password);
+            config.set("sasl.password", password.resolve(prefix)?);
         }
         KafkaAuth::SaslScramSha512 { username, password } => {
             config.set("security.protocol", "SASL_PLAINTEXT");
             config.set("sasl.mechanism", "SCRAM-SHA-512");
             config.set("sasl.username", username);
-            config.set("sasl.password", password);
+            config.set("sasl.password", password.resolve(prefix)?);
         }
         KafkaAuth::SaslGssapi { principal, keytab_path } => {
             config.set("security.protocol", "SASL_PLAINTEXT");
@@ -45,6 +124,30 @@ pub fn create_admin_client(cluster_config: &KafkaClusterConfig) -> anyhow::Resul
                 config.set("sasl.kerberos.keytab", keytab);
             }
         }
+        KafkaAuth::SaslOauthBearer {
+            token_endpoint_url,
+            client_id,
+            client_secret,
+            scope,
+            extensions,
+        } => {
+            config.set("security.protocol", "SASL_PLAINTEXT");
+            config.set("sasl.mechanism", "OAUTHBEARER");
+
+            config.set("sasl.oauthbearer.method", "oidc");
+            config.set("sasl.oauthbearer.token.endpoint.url", token_endpoint_url);
+            config.set("sasl.oauthbearer.client.id", client_id);
+            config.set("sasl.oauthbearer.client.secret", client_secret.resolve(prefix)?);
+            if let Some(scope) = scope {
+                config.set("sasl.oauthbearer.scope", scope);
+            }
+
+            if !extensions.is_empty() {
+                let mut extensions: Vec<_> = extensions.iter().map(|(k, v)| format!("{k}={v}")).collect();
+                extensions.sort();
+                config.set("sasl.oauthbearer.extensions", extensions.join(","));
+            }
+        }
     }
 
     // Set TLS configuration if provided
@@ -70,6 +173,40 @@ pub fn create_admin_client(cluster_config: &KafkaClusterConfig) -> anyhow::Resul
             config.set("ssl.key.location", client_key);
         }
 
+        if let Some(ca_pem) = &tls.ca_pem {
+            config.set("ssl.ca.pem", ca_pem.resolve(prefix)?);
+        }
+
+        if let Some(client_cert_pem) = &tls.client_cert_pem {
+            config.set("ssl.certificate.pem", client_cert_pem.resolve(prefix)?);
+        }
+
+        if let Some(client_key_pem) = &tls.client_key_pem {
+            config.set("ssl.key.pem", client_key_pem.resolve(prefix)?);
+        }
+
+        if let Some(key_password) = &tls.client_key_password {
+            config.set("ssl.key.password", key_password.resolve(prefix)?);
+        }
+
+        if let Some(keystore) = &tls.keystore_path {
+            config.set("ssl.keystore.location", keystore);
+        }
+
+        if let Some(keystore_password) = &tls.keystore_password {
+            config.set("ssl.keystore.password", keystore_password.resolve(prefix)?);
+        }
+
+        match tls.endpoint_identification {
+            Some(true) => {
+                config.set("ssl.endpoint.identification.algorithm", "https");
+            }
+            Some(false) => {
+                config.set("ssl.endpoint.identification.algorithm", "none");
+            }
+            None => {}
+        }
+
         if !tls.verify_certificate {
             config.set("enable.ssl.certificate.verification", "false");
         }
@@ -80,8 +217,90 @@ pub fn create_admin_client(cluster_config: &KafkaClusterConfig) -> anyhow::Resul
         config.set(key, value);
     }
 
-    // Create admin client
-    config.create().context("Failed to create Kafka admin client")
+    Ok((config, context))
+}
+
+/// Check that the brokers behind a client report the cluster ID pinned in its config, so a
+/// copy-pasted bootstrap address can't point one cluster name at another cluster.
+pub fn verify_cluster_id(
+    cluster_name: &str,
+    cluster_config: &KafkaClusterConfig,
+    client: &KafkaAdminClient,
+    timeout: Duration,
+) -> anyhow::Result<()> {
+    let Some(expected) = &cluster_config.cluster_id else {
+        return Ok(());
+    };
+
+    match client.inner().fetch_cluster_id(timeout) {
+        Some(observed) if &observed == expected => Ok(()),
+        Some(observed) => bail!(
+            "Cluster '{}' is pinned to cluster_id '{}', but brokers at '{}' report '{}'; refusing to manage it",
+            cluster_name,
+            expected,
+            cluster_config.bootstrap_servers,
+            observed
+        ),
+        None => bail!(
+            "Cluster '{}' is pinned to cluster_id '{}', but the cluster ID could not be fetched from '{}'",
+            cluster_name,
+            expected,
+            cluster_config.bootstrap_servers
+        ),
+    }
+}
+
+/// Maximum number of topics described in a single DescribeConfigs request
+const DESCRIBE_CONFIGS_BATCH_SIZE: usize = 500;
+
+/// Fetch the config entries for many topics in as few DescribeConfigs requests as possible.
+/// Topics the brokers return an error for are left out of the result.
+pub async fn describe_topic_configs(
+    client: &KafkaAdminClient,
+    topics: &[String],
+    timeout: Duration,
+) -> anyhow::Result<HashMap<String, Vec<ConfigEntry>>> {
+    let opts = AdminOptions::new().operation_timeout(Some(timeout));
+    let mut configs = HashMap::new();
+
+    for batch in topics.chunks(DESCRIBE_CONFIGS_BATCH_SIZE) {
+        let specifiers: Vec<ResourceSpecifier> = batch.iter().map(|topic| ResourceSpecifier::Topic(topic)).collect();
+
+        for result in client.describe_configs(&specifiers, &opts).await? {
+            match result {
+                Ok(ConfigResource {
+                    specifier: OwnedResourceSpecifier::Topic(topic),
+                    entries,
+                }) => {
+                    configs.insert(topic, entries);
+                }
+                Ok(_) => {}
+                Err(e) => tracing::debug!("describe_configs returned an error for one topic: {:?}", e),
+            }
+        }
+    }
+
+    Ok(configs)
+}
+
+/// Fetch one broker's config entries
+pub async fn describe_broker_config(
+    client: &KafkaAdminClient,
+    broker_id: i32,
+    timeout: Duration,
+) -> anyhow::Result<Vec<ConfigEntry>> {
+    let opts = AdminOptions::new().operation_timeout(Some(timeout));
+
+    match client
+        .describe_configs([&ResourceSpecifier::Broker(broker_id)], &opts)
+        .await?
+        .into_iter()
+        .next()
+    {
+        Some(Ok(ConfigResource { entries, .. })) => Ok(entries),
+        Some(Err(e)) => bail!("Failed to describe config of broker {}: {:?}", broker_id, e),
+        None => bail!("No config returned for broker {}", broker_id),
+    }
 }
 
 pub fn get_operation_timeout(timeout_ms: u64) -> Duration {
@@ -1,10 +1,64 @@
-use autoschematic_core::{connector::Resource, macros::FieldTypes, util::PrettyConfig, util::RON};
+use autoschematic_core::{
+    connector::Resource,
+    connector_util::read_mounted_secret,
+    diag::{Diagnostic, DiagnosticPosition, DiagnosticSeverity, DiagnosticSpan},
+    macros::FieldTypes,
+    util::PrettyConfig,
+    util::RON,
+};
 use autoschematic_macros::FieldTypes;
 use documented::{Documented, DocumentedFields};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::Path;
 
+use anyhow::Context;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Documented)]
+#[serde(transparent)]
+/// A credential value or a reference to one. `secret://path` reads an autoschematic sealed
+/// secret, which is unsealed for the connector from .secrets/ under the prefix. `env:NAME` reads
+/// the environment variable NAME, `file:/path` reads a file such as a mounted Kubernetes secret,
+/// and anything else is used as a plaintext value.
+pub struct KafkaSecret(pub String);
+
+impl KafkaSecret {
+    /// Resolve the secret to its value. Errors name the reference, never the resolved value.
+    pub fn resolve(&self, prefix: &Path) -> anyhow::Result<String> {
+        if self.is_sealed_ref() {
+            let value = read_mounted_secret(prefix, &self.0)?;
+            Ok(value.trim_end_matches(['\r', '\n']).to_string())
+        } else if let Some(var) = self.0.strip_prefix("env:") {
+            std::env::var(var).with_context(|| format!("Environment variable '{}' for secret is not set", var))
+        } else if let Some(path) = self.0.strip_prefix("file:") {
+            let value = std::fs::read_to_string(path).with_context(|| format!("Failed to read secret file '{}'", path))?;
+            Ok(value.trim_end_matches(['\r', '\n']).to_string())
+        } else {
+            Ok(self.0.clone())
+        }
+    }
+
+    /// Whether this secret is stored in plaintext rather than referenced
+    pub fn is_plaintext(&self) -> bool {
+        !(self.0.starts_with("env:") || self.0.starts_with("file:") || self.is_sealed_ref())
+    }
+
+    /// Whether this is an autoschematic secret reference
+    pub fn is_sealed_ref(&self) -> bool {
+        self.0.starts_with("secret://")
+    }
+}
+
+impl std::fmt::Debug for KafkaSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_plaintext() {
+            f.write_str("KafkaSecret(<redacted>)")
+        } else {
+            write!(f, "KafkaSecret({:?})", self.0)
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Documented, DocumentedFields)]
 #[serde(deny_unknown_fields)]
 /// Authentication mechanism for Kafka cluster connection
@@ -12,13 +66,23 @@ pub enum KafkaAuth {
     /// No authentication (plain connection)
     None,
     /// SASL/PLAIN authentication with username and password
-    SaslPlain { username: String, password: String },
+    SaslPlain { username: String, password: KafkaSecret },
     /// SASL/SCRAM-SHA-256 authentication
-    SaslScramSha256 { username: String, password: String },
+    SaslScramSha256 { username: String, password: KafkaSecret },
     /// SASL/SCRAM-SHA-512 authentication
-    SaslScramSha512 { username: String, password: String },
+    SaslScramSha512 { username: String, password: KafkaSecret },
     /// Kerberos/GSSAPI authentication
     SaslGssapi { principal: String, keytab_path: Option<String> },
+    /// SASL/OAUTHBEARER authentication using OIDC client credentials, with tokens fetched
+    /// and refreshed by librdkafka
+    SaslOauthBearer {
+        token_endpoint_url: String,
+        client_id: String,
+        client_secret: KafkaSecret,
+        scope: Option<String>,
+        #[serde(default)]
+        extensions: HashMap<String, String>,
+    },
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Documented, DocumentedFields, FieldTypes)]
@@ -31,8 +95,22 @@ pub struct KafkaTlsConfig {
     pub client_cert_path: Option<String>,
     /// Path to client private key file for mutual TLS authentication
     pub client_key_path: Option<String>,
+    /// Inline PEM CA certificate(s), as a value or secret reference (optional)
+    pub ca_pem: Option<KafkaSecret>,
+    /// Inline PEM client certificate, as a value or secret reference (optional)
+    pub client_cert_pem: Option<KafkaSecret>,
+    /// Inline PEM client private key, as a value or secret reference (optional)
+    pub client_key_pem: Option<KafkaSecret>,
+    /// Password for an encrypted client private key (optional)
+    pub client_key_password: Option<KafkaSecret>,
+    /// Path to a PKCS#12 keystore holding the client certificate and key (optional)
+    pub keystore_path: Option<String>,
+    /// Password for the PKCS#12 keystore (optional)
+    pub keystore_password: Option<KafkaSecret>,
     /// Whether to verify the broker's SSL certificate (default: true)
     pub verify_certificate: bool,
+    /// Whether to check the broker hostname against its certificate; unset keeps the librdkafka default (optional)
+    pub endpoint_identification: Option<bool>,
 }
 
 impl Default for KafkaTlsConfig {
@@ -41,7 +119,36 @@ impl Default for KafkaTlsConfig {
             ca_cert_path: None,
             client_cert_path: None,
             client_key_path: None,
+            ca_pem: None,
+            client_cert_pem: None,
+            client_key_pem: None,
+            client_key_password: None,
+            keystore_path: None,
+            keystore_password: None,
             verify_certificate: true,
+            endpoint_identification: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default, Documented, DocumentedFields)]
+/// What the connector may change in a cluster
+pub enum KafkaClusterMode {
+    /// All operations are allowed
+    #[default]
+    ReadWrite,
+    /// The cluster can be imported and diffed, but every operation is rejected
+    ReadOnly,
+    /// Creates and updates are allowed, but deletions and destructive tasks are rejected
+    Protected,
+}
+
+impl std::fmt::Display for KafkaClusterMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KafkaClusterMode::ReadWrite => write!(f, "read-write"),
+            KafkaClusterMode::ReadOnly => write!(f, "read-only"),
+            KafkaClusterMode::Protected => write!(f, "protected"),
         }
     }
 }
@@ -58,6 +165,15 @@ pub struct KafkaClusterConfig {
     pub tls: Option<KafkaTlsConfig>,
     /// Additional client configuration properties as key-value pairs
     pub additional_config: HashMap<String, String>,
+    /// What the connector may change in this cluster (default: ReadWrite)
+    #[serde(default)]
+    pub mode: KafkaClusterMode,
+    /// Expected Kafka cluster ID. When set, the connector refuses to manage this cluster
+    /// if the brokers report a different ID (optional)
+    pub cluster_id: Option<String>,
+    /// Maximum total number of partitions allowed across all topics in this cluster,
+    /// checked when planning topic changes (optional)
+    pub max_partitions: Option<i32>,
 }
 
 impl Default for KafkaClusterConfig {
@@ -67,6 +183,9 @@ impl Default for KafkaClusterConfig {
             auth: KafkaAuth::None,
             tls: None,
             additional_config: HashMap::new(),
+            mode: KafkaClusterMode::ReadWrite,
+            cluster_id: None,
+            max_partitions: None,
         }
     }
 }
@@ -81,6 +200,31 @@ pub struct KafkaConnectorConfig {
     pub operation_timeout_ms: u64,
     /// Maximum number of concurrent requests to Kafka clusters (default: 10)
     pub concurrent_requests: usize,
+    /// Send topic operations with the Admin API's validate_only flag, so the brokers
+    /// check them without applying anything (default: false)
+    #[serde(default)]
+    pub validate_only: bool,
+    /// How long topic metadata and configs fetched in bulk are reused by `get`, in seconds.
+    /// 0 disables the cache (default: 30)
+    #[serde(default = "default_cache_ttl_secs")]
+    pub cache_ttl_secs: u64,
+    /// Before deleting a topic, refuse if any partition was written to within this many
+    /// minutes. 0 disables the check (default: 60)
+    #[serde(default = "default_delete_check_window_minutes")]
+    pub delete_check_window_minutes: u64,
+    /// If set, every topic is archived under this directory before it is deleted, and the
+    /// delete is abandoned if archiving fails. Relative paths are resolved against the
+    /// repository root. See the archive_topic task for the format (default: none)
+    #[serde(default)]
+    pub archive_dir: Option<String>,
+}
+
+fn default_cache_ttl_secs() -> u64 {
+    30
+}
+
+fn default_delete_check_window_minutes() -> u64 {
+    60
 }
 
 impl Default for KafkaConnectorConfig {
@@ -92,11 +236,123 @@ impl Default for KafkaConnectorConfig {
             clusters,
             operation_timeout_ms: 30000,
             concurrent_requests: 10,
+            validate_only: false,
+            cache_ttl_secs: default_cache_ttl_secs(),
+            delete_check_window_minutes: default_delete_check_window_minutes(),
+            archive_dir: None,
         }
     }
 }
 
+/// Which clusters differ between two connector configs
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct KafkaConfigDiff {
+    /// Clusters only present in the new config
+    pub added: Vec<String>,
+    /// Clusters only present in the old config
+    pub removed: Vec<String>,
+    /// Clusters whose connection settings (brokers, auth, TLS, client properties or pinned ID) changed
+    pub reconnected: Vec<String>,
+}
+
+impl KafkaConfigDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.reconnected.is_empty()
+    }
+
+    /// Clusters whose existing connections can no longer be used
+    pub fn stale_clusters(&self) -> impl Iterator<Item = &String> {
+        self.removed.iter().chain(self.reconnected.iter())
+    }
+
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if !self.added.is_empty() {
+            parts.push(format!("add clusters {}", self.added.join(", ")));
+        }
+        if !self.removed.is_empty() {
+            parts.push(format!("remove clusters {}", self.removed.join(", ")));
+        }
+        if !self.reconnected.is_empty() {
+            parts.push(format!("reconnect clusters {}", self.reconnected.join(", ")));
+        }
+        if parts.is_empty() {
+            String::from("no cluster connection changes")
+        } else {
+            parts.join("; ")
+        }
+    }
+}
+
+impl KafkaClusterConfig {
+    /// Replace plaintext secrets with placeholder `env:` references, so the config can be
+    /// shown or written out without echoing credentials
+    pub fn redact_plaintext_secrets(&mut self, cluster_name: &str) {
+        let mut secrets: Vec<(&str, &mut KafkaSecret)> = Vec::new();
+
+        match &mut self.auth {
+            KafkaAuth::SaslPlain { password, .. }
+            | KafkaAuth::SaslScramSha256 { password, .. }
+            | KafkaAuth::SaslScramSha512 { password, .. } => secrets.push(("password", password)),
+            KafkaAuth::SaslOauthBearer { client_secret, .. } => secrets.push(("client_secret", client_secret)),
+            KafkaAuth::None | KafkaAuth::SaslGssapi { .. } => {}
+        }
+
+        if let Some(tls) = &mut self.tls {
+            for (field, secret) in [
+                ("ca_pem", &mut tls.ca_pem),
+                ("client_cert_pem", &mut tls.client_cert_pem),
+                ("client_key_pem", &mut tls.client_key_pem),
+                ("client_key_password", &mut tls.client_key_password),
+                ("keystore_password", &mut tls.keystore_password),
+            ] {
+                if let Some(secret) = secret {
+                    secrets.push((field, secret));
+                }
+            }
+        }
+
+        for (field, secret) in secrets {
+            if secret.is_plaintext() {
+                *secret = KafkaSecret(format!("env:[{}_{}]", cluster_name, field));
+            }
+        }
+    }
+
+    /// Whether switching from `self` to `other` requires a new admin client
+    pub fn connection_differs(&self, other: &Self) -> bool {
+        self.bootstrap_servers != other.bootstrap_servers
+            || self.auth != other.auth
+            || self.tls != other.tls
+            || self.additional_config != other.additional_config
+            || self.cluster_id != other.cluster_id
+    }
+}
+
 impl KafkaConnectorConfig {
+    pub fn diff(&self, new: &Self) -> KafkaConfigDiff {
+        let mut diff = KafkaConfigDiff::default();
+
+        for (name, new_cluster) in &new.clusters {
+            match self.clusters.get(name) {
+                None => diff.added.push(name.clone()),
+                Some(old_cluster) if old_cluster.connection_differs(new_cluster) => diff.reconnected.push(name.clone()),
+                Some(_) => {}
+            }
+        }
+
+        for name in self.clusters.keys() {
+            if !new.clusters.contains_key(name) {
+                diff.removed.push(name.clone());
+            }
+        }
+
+        diff.added.sort();
+        diff.removed.sort();
+        diff.reconnected.sort();
+        diff
+    }
+
     pub fn try_load(prefix: &Path) -> anyhow::Result<Option<Self>> {
         let config_path = prefix.join("kafka").join("config.ron");
 
@@ -108,6 +364,57 @@ impl KafkaConnectorConfig {
         let config: KafkaConnectorConfig = RON.from_str(&config_str)?;
         Ok(Some(config))
     }
+
+    /// Warnings for settings in this config that parse fine but weaken security. `s` is the
+    /// text the config was parsed from, and is only used to place each warning.
+    pub fn diag_warnings(&self, s: &str) -> Vec<Diagnostic> {
+        let mut clusters: Vec<(&String, &KafkaClusterConfig)> = self.clusters.iter().collect();
+        clusters.sort_by_key(|(name, _)| *name);
+
+        clusters
+            .into_iter()
+            .filter(|(_, cluster)| cluster.tls.as_ref().is_some_and(|tls| !tls.verify_certificate))
+            .map(|(name, _)| Diagnostic {
+                severity: DiagnosticSeverity::WARNING as u8,
+                span: span_after(s, &[&format!("\"{}\"", name), "verify_certificate"]),
+                message: format!(
+                    "Cluster '{}' sets verify_certificate: false, which disables broker certificate verification; \
+                     connections are open to interception",
+                    name
+                ),
+            })
+            .collect()
+    }
+}
+
+/// The span from the last of `needles` to the end of its line, finding each needle after the
+/// one before it. Falls back to the first line when a needle isn't found.
+fn span_after(s: &str, needles: &[&str]) -> DiagnosticSpan {
+    let mut offset = 0;
+    for needle in needles {
+        match s[offset..].find(needle) {
+            Some(found) => offset += found,
+            None => {
+                offset = 0;
+                break;
+            }
+        }
+    }
+
+    let line = s[..offset].matches('\n').count();
+    let line_start = s[..offset].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let line_len = s[line_start..].find('\n').unwrap_or(s.len() - line_start);
+
+    DiagnosticSpan {
+        start: DiagnosticPosition {
+            line: line as u32 + 1,
+            col: (offset - line_start) as u32 + 1,
+        },
+        end: DiagnosticPosition {
+            line: line as u32 + 1,
+            col: line_len as u32 + 1,
+        },
+    }
 }
 
 impl Resource for KafkaConnectorConfig {
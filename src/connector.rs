@@ -1,12 +1,31 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     path::{Path, PathBuf},
     sync::Arc,
+    time::{Duration, Instant},
 };
 
-use crate::client::{KafkaAdminClient, create_admin_client};
-use crate::resource::{self, KafkaAcl, KafkaQuota, KafkaTopic};
-use crate::{addr::KafkaResourceAddress, config::KafkaConnectorConfig};
+use crate::client::{
+    CONNECT_RETRY_BACKOFF, KafkaAdminClient, KafkaClusterState, create_admin_client, describe_broker_config,
+    describe_topic_configs, get_operation_timeout, verify_cluster_id,
+};
+use crate::connector::cache::KafkaClusterCacheSlot;
+use crate::resource::{
+    self, KafkaAccess, KafkaAcl, KafkaAclSet, KafkaConfigValue, KafkaConsumeAccess, KafkaPrincipal, KafkaQuota,
+    KafkaResourceMatch, KafkaSharedTopic, KafkaTopic, KafkaTopicLifecycle, KafkaTopicOverride, KafkaTopicProfile,
+    KafkaTopicSpec,
+};
+use crate::task::{
+    KafkaArchiveTopicTask, KafkaCopyTopicTask, KafkaEffectiveAccessTask, KafkaOrphanAuditTask, KafkaRestoreTopicTask,
+    KafkaTask,
+};
+use crate::{
+    acl::{self, KafkaAclFilter},
+    addr::KafkaResourceAddress,
+    config::{KafkaClusterConfig, KafkaClusterMode, KafkaConfigDiff, KafkaConnectorConfig},
+    policy, profile,
+};
+use anyhow::{Context, anyhow, bail};
 use async_trait::async_trait;
 use autoschematic_core::{
     connector::{
@@ -15,22 +34,40 @@ use autoschematic_core::{
     },
     diag::DiagnosticResponse,
     doc_dispatch, skeleton,
-    util::{ron_check_eq, ron_check_syntax},
+    util::{RON, ron_check_eq, ron_check_syntax},
 };
 use indexmap::IndexMap;
-use tokio::sync::{RwLock, Semaphore};
-
+use rdkafka::{admin::ConfigEntry, metadata::Metadata};
+use rdkafka_autoschematic_fork as rdkafka;
+use tokio::sync::{Mutex, OwnedSemaphorePermit, RwLock, Semaphore};
+
+pub mod cache;
+pub mod copy_topic;
+pub mod delete_check;
+pub mod effective_access;
 pub mod get;
 pub mod list;
 pub mod op_exec;
+pub mod orphan_audit;
 pub mod plan;
+pub mod task_exec;
+
+/// A cluster's connection state, locked while connecting. None until the first attempt.
+type KafkaClientSlot = Arc<Mutex<Option<KafkaClusterState>>>;
 
 pub struct KafkaConnector {
     prefix: PathBuf,
-    /// Map of cluster name to Kafka admin client
-    clients: RwLock<HashMap<String, KafkaAdminClient>>,
+    /// Map of cluster name to Kafka admin client, created lazily on first use. Each cluster
+    /// has its own slot, so connecting to one cluster never waits on another.
+    clients: RwLock<HashMap<String, KafkaClientSlot>>,
+    /// Admin clients for clusters that are only staged, each kept with the settings it was built
+    /// from, so it's only rebuilt when the staged connection settings change
+    staged_clients: Mutex<HashMap<String, (KafkaClusterConfig, Arc<KafkaAdminClient>)>>,
     config: RwLock<KafkaConnectorConfig>,
-    semaphore: RwLock<Semaphore>,
+    /// Bounds concurrent requests to the clusters, sized by `concurrent_requests`
+    semaphore: RwLock<Arc<Semaphore>>,
+    /// Per-cluster topic cache used by `get`, see `cache_ttl_secs`
+    cache: Mutex<HashMap<String, KafkaClusterCacheSlot>>,
 }
 
 impl Default for KafkaConnector {
@@ -38,9 +75,313 @@ impl Default for KafkaConnector {
         Self {
             prefix: Default::default(),
             clients: RwLock::new(HashMap::new()),
+            staged_clients: Mutex::new(HashMap::new()),
             config: Default::default(),
-            semaphore: RwLock::new(Semaphore::const_new(1)),
+            semaphore: RwLock::new(Arc::new(Semaphore::const_new(1))),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl KafkaConnector {
+    /// Get the admin client for a cluster, creating and verifying it on first use. A cluster
+    /// that fails to connect only fails operations on that cluster, and is retried once
+    /// `CONNECT_RETRY_BACKOFF` has passed.
+    pub async fn client(&self, cluster: &str) -> anyhow::Result<Arc<KafkaAdminClient>> {
+        // Clusters that are only staged get a client of their own, kept apart from the live
+        // clients and dropped once the config is applied
+        if let Some((cluster_config, timeout)) = self.staged_cluster_config(cluster).await? {
+            if let Some((built_from, client)) = self.staged_clients.lock().await.get(cluster)
+                && !built_from.connection_differs(&cluster_config)
+            {
+                return Ok(client.clone());
+            }
+
+            let prefix = self.prefix.clone();
+            let cluster_name = cluster.to_string();
+            let staged_config = cluster_config.clone();
+            let client = tokio::task::spawn_blocking(move || {
+                let client = create_admin_client(&prefix, &staged_config)?;
+                verify_cluster_id(&cluster_name, &staged_config, &client, timeout)?;
+                Ok::<_, anyhow::Error>(client)
+            })
+            .await?
+            .with_context(|| format!("Failed to connect to staged cluster '{}'", cluster))?;
+
+            let client = Arc::new(client);
+            self.staged_clients
+                .lock()
+                .await
+                .insert(cluster.to_string(), (cluster_config, client.clone()));
+            return Ok(client);
+        }
+
+        let slot = self.client_slot(cluster).await;
+        let mut state = slot.lock().await;
+
+        match &*state {
+            Some(KafkaClusterState::Ready(client)) => return Ok(client.clone()),
+            Some(KafkaClusterState::Failed { error, at }) if at.elapsed() < CONNECT_RETRY_BACKOFF => {
+                bail!("Cluster '{}' is unavailable: {}", cluster, error)
+            }
+            _ => {}
+        }
+
+        let (cluster_config, timeout) = self.cluster_config(cluster).await?;
+
+        let prefix = self.prefix.clone();
+        let cluster_name = cluster.to_string();
+        let connect = tokio::task::spawn_blocking(move || {
+            let client = create_admin_client(&prefix, &cluster_config)?;
+            verify_cluster_id(&cluster_name, &cluster_config, &client, timeout)?;
+            Ok::<_, anyhow::Error>(client)
+        });
+
+        match connect.await? {
+            Ok(client) => {
+                let client = Arc::new(client);
+                *state = Some(KafkaClusterState::Ready(client.clone()));
+                Ok(client)
+            }
+            Err(e) => {
+                tracing::error!("Failed to connect to cluster '{}': {:#}", cluster, e);
+                let error = format!("{:#}", e);
+                *state = Some(KafkaClusterState::Failed {
+                    error: error.clone(),
+                    at: Instant::now(),
+                });
+                bail!("Cluster '{}' is unavailable: {}", cluster, error)
+            }
+        }
+    }
+
+    /// The connection slot for a cluster, created empty if the cluster hasn't been used yet.
+    /// The map lock is only held to find or insert the slot.
+    async fn client_slot(&self, cluster: &str) -> KafkaClientSlot {
+        if let Some(slot) = self.clients.read().await.get(cluster) {
+            return slot.clone();
+        }
+
+        self.clients.write().await.entry(cluster.to_string()).or_default().clone()
+    }
+
+    /// Swap in a new config. Clients and cached state are dropped only for clusters that were
+    /// removed or whose connection settings changed, and for clusters that previously failed
+    /// to connect; connections to unchanged clusters are kept.
+    pub async fn reload_config(&self, new_config: KafkaConnectorConfig) -> KafkaConfigDiff {
+        let mut config = self.config.write().await;
+        let diff = config.diff(&new_config);
+
+        {
+            let mut clients = self.clients.write().await;
+            for cluster in diff.stale_clusters() {
+                clients.remove(cluster);
+            }
+            // Retry failed clusters with the new config right away. Slots that are connecting
+            // are kept; they connect with the config they started with.
+            clients.retain(|_, slot| {
+                slot.try_lock()
+                    .map(|state| matches!(*state, Some(KafkaClusterState::Ready(_))))
+                    .unwrap_or(true)
+            });
+        }
+
+        for cluster in diff.stale_clusters() {
+            self.invalidate_cache(cluster).await;
+        }
+
+        // Staged clusters that are now live connect through their own slots
+        self.staged_clients
+            .lock()
+            .await
+            .retain(|cluster, _| !new_config.clusters.contains_key(cluster));
+
+        *self.semaphore.write().await = Arc::new(Semaphore::new(new_config.concurrent_requests.max(1)));
+        *config = new_config;
+
+        diff
+    }
+
+    /// Settings for a cluster that only kafka/config.ron on disk has. A change can add a cluster
+    /// alongside its topics, and the live config only gains the cluster when that change's
+    /// ReloadConfig op runs, so get and plan read such clusters from the staged file.
+    /// Returns None for clusters in the live config.
+    async fn staged_cluster_config(&self, cluster: &str) -> anyhow::Result<Option<(KafkaClusterConfig, Duration)>> {
+        if self.config.read().await.clusters.contains_key(cluster) {
+            return Ok(None);
+        }
+
+        let Some(staged) = KafkaConnectorConfig::try_load(&self.prefix)? else {
+            return Ok(None);
+        };
+        let timeout = get_operation_timeout(staged.operation_timeout_ms);
+        Ok(staged
+            .clusters
+            .get(cluster)
+            .map(|cluster_config| (cluster_config.clone(), timeout)))
+    }
+
+    /// The cluster settings a plan is made against: the live config's, or the staged file's for
+    /// clusters that aren't live yet
+    async fn planned_cluster_config(&self, cluster: &str) -> anyhow::Result<KafkaClusterConfig> {
+        match self.staged_cluster_config(cluster).await? {
+            Some((cluster_config, _)) => Ok(cluster_config),
+            None => Ok(self.cluster_config(cluster).await?.0),
+        }
+    }
+
+    /// The configured mode for a cluster
+    pub async fn cluster_mode(&self, cluster: &str) -> anyhow::Result<KafkaClusterMode> {
+        let config = self.config.read().await;
+        let cluster_config = config
+            .clusters
+            .get(cluster)
+            .ok_or_else(|| anyhow!("Cluster '{}' not found in configuration", cluster))?;
+        Ok(cluster_config.mode)
+    }
+
+    /// Reject a change that the cluster's mode forbids. `destructive` marks deletions and
+    /// destructive tasks, which protected clusters refuse.
+    pub async fn check_cluster_mode(&self, cluster: &str, destructive: bool) -> anyhow::Result<()> {
+        match self.cluster_mode(cluster).await? {
+            KafkaClusterMode::ReadWrite => Ok(()),
+            KafkaClusterMode::ReadOnly => bail!("Cluster '{}' is read-only; refusing to modify it", cluster),
+            KafkaClusterMode::Protected if destructive => {
+                bail!("Cluster '{}' is protected; refusing destructive operation", cluster)
+            }
+            KafkaClusterMode::Protected => Ok(()),
+        }
+    }
+
+    /// A cluster's connection settings and the operation timeout, for building clients
+    /// other than the shared admin client
+    async fn cluster_config(&self, cluster: &str) -> anyhow::Result<(KafkaClusterConfig, Duration)> {
+        let config = self.config.read().await;
+        let cluster_config = config
+            .clusters
+            .get(cluster)
+            .ok_or_else(|| anyhow!("Cluster '{}' not found in configuration", cluster))?
+            .clone();
+        Ok((cluster_config, get_operation_timeout(config.operation_timeout_ms)))
+    }
+
+    /// Wait for a slot under the connector's concurrent request limit
+    async fn permit(&self) -> anyhow::Result<OwnedSemaphorePermit> {
+        let semaphore = self.semaphore.read().await.clone();
+        Ok(semaphore.acquire_owned().await?)
+    }
+
+    /// Fetch metadata for a cluster, or a single topic in it. librdkafka blocks for this call,
+    /// so it runs on the blocking pool under the concurrent request limit.
+    pub async fn fetch_metadata(&self, cluster: &str, topic: Option<&str>) -> anyhow::Result<Metadata> {
+        let client = self.client(cluster).await?;
+        let timeout = get_operation_timeout(self.config.read().await.operation_timeout_ms);
+        let _permit = self.permit().await?;
+
+        let topic = topic.map(String::from);
+        tokio::task::spawn_blocking(move || client.inner().fetch_metadata(topic.as_deref(), timeout))
+            .await?
+            .with_context(|| format!("Failed to fetch metadata for cluster '{}'", cluster))
+    }
+
+    /// Names of the consumer groups in a cluster
+    pub async fn list_groups(&self, cluster: &str) -> anyhow::Result<Vec<String>> {
+        let client = self.client(cluster).await?;
+        let timeout = get_operation_timeout(self.config.read().await.operation_timeout_ms);
+        let _permit = self.permit().await?;
+
+        tokio::task::spawn_blocking(move || {
+            let groups = client.inner().fetch_group_list(None, timeout)?;
+            Ok::<_, anyhow::Error>(groups.groups().iter().map(|g| g.name().to_string()).collect())
+        })
+        .await?
+        .with_context(|| format!("Failed to list consumer groups in cluster '{}'", cluster))
+    }
+
+    /// Fetch config entries for many topics in a cluster with batched DescribeConfigs requests
+    pub async fn describe_topic_configs(
+        &self,
+        cluster: &str,
+        topics: &[String],
+    ) -> anyhow::Result<HashMap<String, Vec<ConfigEntry>>> {
+        let client = self.client(cluster).await?;
+        let timeout = get_operation_timeout(self.config.read().await.operation_timeout_ms);
+        let _permit = self.permit().await?;
+
+        describe_topic_configs(&client, topics, timeout)
+            .await
+            .with_context(|| format!("Failed to describe topic configs in cluster '{}'", cluster))
+    }
+
+    /// Fetch the config entries of one broker in a cluster
+    pub async fn describe_broker_config(&self, cluster: &str, broker_id: i32) -> anyhow::Result<Vec<ConfigEntry>> {
+        let client = self.client(cluster).await?;
+        let timeout = get_operation_timeout(self.config.read().await.operation_timeout_ms);
+        let _permit = self.permit().await?;
+
+        describe_broker_config(&client, broker_id, timeout)
+            .await
+            .with_context(|| format!("Failed to describe broker config in cluster '{}'", cluster))
+    }
+
+    /// ACL bindings in a cluster matching a filter
+    pub async fn describe_acls(&self, cluster: &str, filter: KafkaAclFilter) -> anyhow::Result<Vec<KafkaAcl>> {
+        let client = self.client(cluster).await?;
+        let timeout = get_operation_timeout(self.config.read().await.operation_timeout_ms);
+        let _permit = self.permit().await?;
+
+        tokio::task::spawn_blocking(move || acl::describe_acls(&client, &filter, timeout))
+            .await?
+            .with_context(|| format!("Failed to describe ACLs in cluster '{}'", cluster))
+    }
+
+    /// Create ACL bindings in a cluster
+    pub async fn create_acls(&self, cluster: &str, acls: Vec<KafkaAcl>) -> anyhow::Result<()> {
+        let client = self.client(cluster).await?;
+        let timeout = get_operation_timeout(self.config.read().await.operation_timeout_ms);
+        let _permit = self.permit().await?;
+
+        tokio::task::spawn_blocking(move || acl::create_acls(&client, &acls, timeout))
+            .await?
+            .with_context(|| format!("Failed to create ACLs in cluster '{}'", cluster))
+    }
+
+    /// Delete ACL bindings in a cluster by exact match, returning how many were deleted
+    pub async fn delete_acls(&self, cluster: &str, acls: Vec<KafkaAcl>) -> anyhow::Result<usize> {
+        let client = self.client(cluster).await?;
+        let timeout = get_operation_timeout(self.config.read().await.operation_timeout_ms);
+        let _permit = self.permit().await?;
+
+        tokio::task::spawn_blocking(move || acl::delete_acls(&client, &acls, timeout))
+            .await?
+            .with_context(|| format!("Failed to delete ACLs in cluster '{}'", cluster))
+    }
+
+    /// The current config with each reachable cluster's observed cluster ID filled in,
+    /// so that pinning a cluster is a matter of accepting the skeleton. Plaintext secrets
+    /// are replaced with placeholder references, since skeletons are written to disk.
+    async fn observed_config_skeleton(&self) -> KafkaConnectorConfig {
+        let mut config = self.config.read().await.clone();
+        let timeout = get_operation_timeout(config.operation_timeout_ms);
+
+        for (cluster_name, cluster_config) in config.clusters.iter_mut() {
+            cluster_config.redact_plaintext_secrets(cluster_name);
+
+            match self.client(cluster_name).await {
+                Ok(client) => {
+                    let cluster_id = tokio::task::spawn_blocking(move || client.inner().fetch_cluster_id(timeout))
+                        .await
+                        .ok()
+                        .flatten();
+                    if let Some(cluster_id) = cluster_id {
+                        cluster_config.cluster_id = Some(cluster_id);
+                    }
+                }
+                Err(e) => tracing::warn!("Not recording cluster_id for '{}': {:#}", cluster_name, e),
+            }
         }
+
+        config
     }
 }
 
@@ -59,17 +400,13 @@ impl Connector for KafkaConnector {
     async fn init(&self) -> anyhow::Result<()> {
         let config = KafkaConnectorConfig::try_load(&self.prefix)?.unwrap_or_default();
 
-        // Create admin clients for each cluster
-        let mut clients = HashMap::new();
-        for (cluster_name, cluster_config) in &config.clusters {
-            let client = create_admin_client(cluster_config)?;
-            clients.insert(cluster_name.clone(), client);
+        // Admin clients are created lazily in client(), so that one unreachable
+        // cluster doesn't prevent working with the others.
+        let diff = self.reload_config(config).await;
+        if !diff.is_empty() {
+            tracing::info!("Loaded kafka/config.ron: {}", diff.describe());
         }
 
-        *self.config.write().await = config.clone();
-        *self.semaphore.write().await = Semaphore::new(config.concurrent_requests);
-        *self.clients.write().await = clients;
-
         Ok(())
     }
 
@@ -77,6 +414,7 @@ impl Connector for KafkaConnector {
         if let Ok(addr) = KafkaResourceAddress::from_path(addr) {
             match addr {
                 KafkaResourceAddress::Config => Ok(FilterResponse::Config),
+                KafkaResourceAddress::Task { .. } => Ok(FilterResponse::Task),
                 _ => Ok(FilterResponse::Resource),
             }
         } else {
@@ -119,21 +457,49 @@ impl Connector for KafkaConnector {
     async fn get_skeletons(&self) -> Result<Vec<SkeletonResponse>, anyhow::Error> {
         let mut res = Vec::new();
 
-        res.push(skeleton!(KafkaResourceAddress::Config, KafkaConnectorConfig::default()));
+        res.push(skeleton!(KafkaResourceAddress::Config, self.observed_config_skeleton().await));
 
         let mut topic_config = IndexMap::new();
-        topic_config.insert("retention.ms".to_string(), "604800000".to_string()); // 7 days
-        topic_config.insert("compression.type".to_string(), "snappy".to_string());
+        topic_config.insert("retention.ms".to_string(), KafkaConfigValue::from("7d"));
+        topic_config.insert("compression.type".to_string(), KafkaConfigValue::from("snappy"));
 
         res.push(skeleton!(
             KafkaResourceAddress::Topic {
                 cluster: String::from("[cluster_name]"),
                 topic: String::from("[topic_name]"),
             },
-            resource::KafkaResource::Topic(KafkaTopic {
-                partitions: 3,
-                replication_factor: 2,
-                config: topic_config,
+            resource::KafkaResource::Topic(
+                KafkaTopic {
+                    partitions: 3,
+                    replication_factor: 2,
+                    config: topic_config,
+                    lifecycle: KafkaTopicLifecycle::default(),
+                }
+                .into()
+            )
+        ));
+
+        let mut shared_clusters = BTreeMap::new();
+        shared_clusters.insert(
+            String::from("[dev_cluster_name]"),
+            KafkaTopicOverride {
+                partitions: Some(1),
+                replication_factor: Some(1),
+                ..Default::default()
+            },
+        );
+        shared_clusters.insert(String::from("[prod_cluster_name]"), KafkaTopicOverride::default());
+
+        res.push(skeleton!(
+            KafkaResourceAddress::SharedTopic {
+                topic: String::from("[topic_name]"),
+            },
+            resource::KafkaResource::SharedTopic(KafkaSharedTopic {
+                partitions: 6,
+                replication_factor: 3,
+                config: IndexMap::from([(String::from("retention.ms"), KafkaConfigValue::from("7d"))]),
+                lifecycle: KafkaTopicLifecycle::default(),
+                clusters: shared_clusters,
             })
         ));
 
@@ -142,7 +508,24 @@ impl Connector for KafkaConnector {
                 cluster: String::from("[cluster_name]"),
                 acl_id: String::from("[acl_identifier]"),
             },
-            resource::KafkaResource::Acl(KafkaAcl::default())
+            resource::KafkaResource::Acl(KafkaAclSet {
+                bindings: vec![KafkaAcl::default()]
+            })
+        ));
+
+        res.push(skeleton!(
+            KafkaResourceAddress::Access {
+                cluster: String::from("[cluster_name]"),
+                principal: String::from("[user_name]"),
+            },
+            resource::KafkaResource::Access(KafkaAccess {
+                produces: vec![KafkaResourceMatch::Prefixed(String::from("[topic_prefix]"))],
+                consumes: vec![KafkaConsumeAccess {
+                    topic: KafkaResourceMatch::Literal(String::from("[topic_name]")),
+                    group: KafkaResourceMatch::Literal(String::from("[consumer_group]")),
+                }],
+                ..Default::default()
+            })
         ));
 
         res.push(skeleton!(
@@ -150,7 +533,74 @@ impl Connector for KafkaConnector {
                 cluster: String::from("[cluster_name]"),
                 quota_id: String::from("[quota_identifier]"),
             },
-            resource::KafkaResource::Quota(KafkaQuota::default())
+            resource::KafkaResource::Quota(KafkaQuota {
+                entities: vec![resource::KafkaQuotaEntity {
+                    entity_type: resource::KafkaQuotaEntityType::User,
+                    name: Some(String::from("[user_name]")),
+                }],
+                producer_byte_rate: Some(1048576.0),
+                consumer_byte_rate: Some(2097152.0),
+                ..Default::default()
+            })
+        ));
+
+        res.push(skeleton!(
+            KafkaResourceAddress::Task {
+                kind: KafkaTask::ArchiveTopic
+            },
+            KafkaArchiveTopicTask {
+                cluster: String::from("[cluster_name]"),
+                topic: String::from("[topic_name]"),
+                archive_dir: String::from("kafka-archives"),
+            }
+        ));
+
+        res.push(skeleton!(
+            KafkaResourceAddress::Task {
+                kind: KafkaTask::RestoreTopic
+            },
+            KafkaRestoreTopicTask {
+                cluster: String::from("[cluster_name]"),
+                topic: String::from("[topic_name]"),
+                archive_path: String::from("kafka-archives/[cluster_name]/[topic_name]/[archived_at_ms]"),
+            }
+        ));
+
+        res.push(skeleton!(
+            KafkaResourceAddress::Task {
+                kind: KafkaTask::CopyTopic
+            },
+            KafkaCopyTopicTask {
+                source_cluster: String::from("[source_cluster_name]"),
+                target_cluster: String::from("[target_cluster_name]"),
+                topic: String::from("[topic_name]"),
+                target_topic: None,
+                consumer_groups: vec![String::from("[consumer_group]")],
+            }
+        ));
+
+        res.push(skeleton!(
+            KafkaResourceAddress::Task {
+                kind: KafkaTask::EffectiveAccess
+            },
+            KafkaEffectiveAccessTask {
+                cluster: String::from("[cluster_name]"),
+                principal: String::from("User:[principal_name]"),
+                host: None,
+                topics: Vec::new(),
+                groups: Vec::new(),
+                report_path: None,
+            }
+        ));
+
+        res.push(skeleton!(
+            KafkaResourceAddress::Task {
+                kind: KafkaTask::AuditOrphans
+            },
+            KafkaOrphanAuditTask {
+                cluster: String::from("[cluster_name]"),
+                report_path: None,
+            }
         ));
 
         Ok(res)
@@ -158,17 +608,36 @@ impl Connector for KafkaConnector {
 
     async fn get_docstring(&self, _addr: &Path, ident: DocIdent) -> Result<Option<GetDocResponse>, anyhow::Error> {
         use crate::config::*;
+        use crate::policy::*;
         use crate::resource::*;
+        use crate::task::*;
 
         doc_dispatch!(
             ident,
             [
                 KafkaConnectorConfig,
                 KafkaTopic,
+                KafkaTopicSpec,
+                KafkaTopicProfile,
+                KafkaTopicLifecycle,
+                KafkaSharedTopic,
+                KafkaTopicOverride,
+                KafkaTopicPolicy,
+                KafkaPolicyRule,
                 KafkaAcl,
+                KafkaAclSet,
+                KafkaAccess,
+                KafkaConsumeAccess,
                 KafkaQuota,
+                KafkaQuotaEntity,
                 KafkaClusterConfig,
                 KafkaTlsConfig,
+                KafkaSecret,
+                KafkaArchiveTopicTask,
+                KafkaRestoreTopicTask,
+                KafkaCopyTopicTask,
+                KafkaEffectiveAccessTask,
+                KafkaOrphanAuditTask,
             ],
             [
                 KafkaAuth::None,
@@ -176,9 +645,31 @@ impl Connector for KafkaConnector {
                 KafkaAuth::SaslScramSha256,
                 KafkaAuth::SaslScramSha512,
                 KafkaAuth::SaslGssapi,
+                KafkaAuth::SaslOauthBearer,
+                KafkaClusterMode::ReadWrite,
+                KafkaClusterMode::ReadOnly,
+                KafkaClusterMode::Protected,
+                KafkaConfigValue::Bool,
+                KafkaConfigValue::Int,
+                KafkaConfigValue::String,
                 KafkaResourcePatternType::Literal,
                 KafkaResourcePatternType::Prefixed,
+                KafkaResourcePatternType::Match,
+                KafkaResourcePatternType::Any,
+                KafkaPrincipalType::User,
+                KafkaPrincipalType::Group,
+                KafkaPrincipalType::Custom,
+                KafkaResourceMatch::Literal,
+                KafkaResourceMatch::Prefixed,
                 KafkaQuotaEntityType::User,
+                KafkaQuotaEntityType::ClientId,
+                KafkaQuotaEntityType::Ip,
+                KafkaPolicyCheck::NameMatches,
+                KafkaPolicyCheck::RequiredConfig,
+                KafkaPolicyCheck::ConfigRange,
+                KafkaPolicyCheck::ConfigAllowed,
+                KafkaPolicyCheck::Partitions,
+                KafkaPolicyCheck::ReplicationFactor,
             ]
         )
     }
@@ -188,9 +679,50 @@ impl Connector for KafkaConnector {
 
         match addr {
             KafkaResourceAddress::Config => ron_check_eq::<KafkaConnectorConfig>(a, b),
-            KafkaResourceAddress::Topic { .. } => ron_check_eq::<KafkaTopic>(a, b),
-            KafkaResourceAddress::Acl { .. } => ron_check_eq::<KafkaAcl>(a, b),
+            KafkaResourceAddress::Topic { cluster, .. } => {
+                // Compare after resolving profiles and normalizing units, so that `7d` and
+                // `604800000` are equal, as are a profile and the settings it expands to
+                let a: KafkaTopicSpec = RON.from_str(std::str::from_utf8(a)?)?;
+                let b: KafkaTopicSpec = RON.from_str(std::str::from_utf8(b)?)?;
+                let a = profile::resolve_topic(&self.prefix, &cluster, &a)?;
+                let b = profile::resolve_topic(&self.prefix, &cluster, &b)?;
+                Ok(a.normalized()? == b.normalized()?)
+            }
+            KafkaResourceAddress::SharedTopic { .. } => {
+                // Compare what each cluster ends up with, so moving a setting between the
+                // shared settings and the overrides isn't a change
+                let a: KafkaSharedTopic = RON.from_str(std::str::from_utf8(a)?)?;
+                let b: KafkaSharedTopic = RON.from_str(std::str::from_utf8(b)?)?;
+                let normalize = |t: &KafkaSharedTopic| {
+                    t.resolve_all()
+                        .into_iter()
+                        .map(|(cluster, topic)| Ok((cluster, topic.normalized()?)))
+                        .collect::<anyhow::Result<BTreeMap<_, _>>>()
+                };
+                Ok(normalize(&a)? == normalize(&b)?)
+            }
+            KafkaResourceAddress::Acl { .. } => {
+                // Order and duplicates in an ACL file don't matter
+                let a = KafkaAclSet::parse(std::str::from_utf8(a)?)?;
+                let b = KafkaAclSet::parse(std::str::from_utf8(b)?)?;
+                Ok(a.binding_set() == b.binding_set())
+            }
+            KafkaResourceAddress::Access { principal, .. } => {
+                // Access files are equal if they expand to the same bindings
+                let principal = KafkaPrincipal::from_file_stem(&principal)?;
+                let a: KafkaAccess = RON.from_str(std::str::from_utf8(a)?)?;
+                let b: KafkaAccess = RON.from_str(std::str::from_utf8(b)?)?;
+                Ok(a.bindings(&principal) == b.bindings(&principal))
+            }
             KafkaResourceAddress::Quota { .. } => ron_check_eq::<KafkaQuota>(a, b),
+            KafkaResourceAddress::Profile { .. } => ron_check_eq::<KafkaTopicProfile>(a, b),
+            KafkaResourceAddress::Task { kind } => match kind {
+                KafkaTask::ArchiveTopic => ron_check_eq::<KafkaArchiveTopicTask>(a, b),
+                KafkaTask::RestoreTopic => ron_check_eq::<KafkaRestoreTopicTask>(a, b),
+                KafkaTask::CopyTopic => ron_check_eq::<KafkaCopyTopicTask>(a, b),
+                KafkaTask::EffectiveAccess => ron_check_eq::<KafkaEffectiveAccessTask>(a, b),
+                KafkaTask::AuditOrphans => ron_check_eq::<KafkaOrphanAuditTask>(a, b),
+            },
         }
     }
 
@@ -198,29 +730,144 @@ impl Connector for KafkaConnector {
         let addr = KafkaResourceAddress::from_path(addr)?;
 
         match addr {
-            KafkaResourceAddress::Config => ron_check_syntax::<KafkaConnectorConfig>(a),
-            KafkaResourceAddress::Topic { .. } => ron_check_syntax::<KafkaTopic>(a),
-            KafkaResourceAddress::Acl { .. } => ron_check_syntax::<KafkaAcl>(a),
-            KafkaResourceAddress::Quota { .. } => ron_check_syntax::<KafkaQuota>(a),
+            KafkaResourceAddress::Config => {
+                if let Some(res) = ron_check_syntax::<KafkaConnectorConfig>(a)?
+                    && !res.diagnostics.is_empty()
+                {
+                    return Ok(Some(res));
+                }
+
+                let s = std::str::from_utf8(a)?;
+                let config: KafkaConnectorConfig = RON.from_str(s)?;
+                let diagnostics = config.diag_warnings(s);
+                if diagnostics.is_empty() {
+                    Ok(None)
+                } else {
+                    Ok(Some(DiagnosticResponse { diagnostics }))
+                }
+            }
+            KafkaResourceAddress::Topic { cluster, topic } => {
+                if let Some(res) = ron_check_syntax::<KafkaTopicSpec>(a)?
+                    && !res.diagnostics.is_empty()
+                {
+                    return Ok(Some(res));
+                }
+
+                let s = std::str::from_utf8(a)?;
+                let spec: KafkaTopicSpec = RON.from_str(s)?;
+                let resolved = profile::resolve_topic(&self.prefix, &cluster, &spec)?;
+                let diagnostics = policy::topic_diagnostics(&self.prefix, &cluster, &topic, &resolved, s)?;
+                if diagnostics.is_empty() {
+                    Ok(None)
+                } else {
+                    Ok(Some(DiagnosticResponse { diagnostics }))
+                }
+            }
+            KafkaResourceAddress::SharedTopic { topic } => {
+                if let Some(res) = ron_check_syntax::<KafkaSharedTopic>(a)?
+                    && !res.diagnostics.is_empty()
+                {
+                    return Ok(Some(res));
+                }
+
+                // Each cluster's topic is checked against that cluster's policy
+                let s = std::str::from_utf8(a)?;
+                let shared: KafkaSharedTopic = RON.from_str(s)?;
+                let mut diagnostics = Vec::new();
+                for (cluster, resolved) in shared.resolve_all() {
+                    for mut diagnostic in policy::topic_diagnostics(&self.prefix, &cluster, &topic, &resolved, s)? {
+                        diagnostic.message = format!("[{}] {}", cluster, diagnostic.message);
+                        diagnostics.push(diagnostic);
+                    }
+                }
+
+                if diagnostics.is_empty() {
+                    Ok(None)
+                } else {
+                    Ok(Some(DiagnosticResponse { diagnostics }))
+                }
+            }
+            KafkaResourceAddress::Acl { .. } => {
+                // Files holding a single binding are still accepted
+                let s = std::str::from_utf8(a)?;
+                let Ok(set) = KafkaAclSet::parse(s) else {
+                    return ron_check_syntax::<KafkaAclSet>(a);
+                };
+
+                let diagnostics = set.diag_errors(s);
+                if diagnostics.is_empty() {
+                    Ok(None)
+                } else {
+                    Ok(Some(DiagnosticResponse { diagnostics }))
+                }
+            }
+            KafkaResourceAddress::Access { principal, .. } => {
+                if let Some(res) = ron_check_syntax::<KafkaAccess>(a)?
+                    && !res.diagnostics.is_empty()
+                {
+                    return Ok(Some(res));
+                }
+
+                let s = std::str::from_utf8(a)?;
+                let access: KafkaAccess = RON.from_str(s)?;
+                let diagnostics = access.diag_errors(&principal, s);
+                if diagnostics.is_empty() {
+                    Ok(None)
+                } else {
+                    Ok(Some(DiagnosticResponse { diagnostics }))
+                }
+            }
+            KafkaResourceAddress::Quota { .. } => {
+                if let Some(res) = ron_check_syntax::<KafkaQuota>(a)?
+                    && !res.diagnostics.is_empty()
+                {
+                    return Ok(Some(res));
+                }
+
+                let s = std::str::from_utf8(a)?;
+                let quota: KafkaQuota = RON.from_str(s)?;
+                let diagnostics = quota.diag_errors(s);
+                if diagnostics.is_empty() {
+                    Ok(None)
+                } else {
+                    Ok(Some(DiagnosticResponse { diagnostics }))
+                }
+            }
+            KafkaResourceAddress::Profile { scope, name } => {
+                if let Some(res) = ron_check_syntax::<KafkaTopicProfile>(a)?
+                    && !res.diagnostics.is_empty()
+                {
+                    return Ok(Some(res));
+                }
+
+                let diagnostics = profile::diag_warnings(&self.prefix, &scope, &name, std::str::from_utf8(a)?)?;
+                if diagnostics.is_empty() {
+                    Ok(None)
+                } else {
+                    Ok(Some(DiagnosticResponse { diagnostics }))
+                }
+            }
+            KafkaResourceAddress::Task { kind } => match kind {
+                KafkaTask::ArchiveTopic => ron_check_syntax::<KafkaArchiveTopicTask>(a),
+                KafkaTask::RestoreTopic => ron_check_syntax::<KafkaRestoreTopicTask>(a),
+                KafkaTask::CopyTopic => ron_check_syntax::<KafkaCopyTopicTask>(a),
+                KafkaTask::EffectiveAccess => ron_check_syntax::<KafkaEffectiveAccessTask>(a),
+                KafkaTask::AuditOrphans => ron_check_syntax::<KafkaOrphanAuditTask>(a),
+            },
         }
     }
 
     async fn task_exec(
         &self,
         addr: &Path,
-        _body: Vec<u8>,
+        body: Vec<u8>,
 
         // `arg` sets the initial argument for the task. `arg` is set to None after the first execution.
         _arg: Option<Vec<u8>>,
         // The current state of the task as returned by a previous task_exec(...) call.
         // state always starts as None when a task is first executed.
-        _state: Option<Vec<u8>>,
+        state: Option<Vec<u8>>,
     ) -> anyhow::Result<TaskExecResponse> {
-        let addr = KafkaResourceAddress::from_path(addr)?;
-
-        match addr {
-            KafkaResourceAddress::Task { kind } => Ok(TaskExecResponse::default()),
-            _ => Ok(TaskExecResponse::default()),
-        }
+        self.do_task_exec(addr, body, state).await
     }
 }
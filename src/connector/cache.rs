@@ -0,0 +1,116 @@
+use anyhow::bail;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use rdkafka::metadata::MetadataTopic;
+use rdkafka_autoschematic_fork as rdkafka;
+use tokio::sync::Mutex;
+
+use crate::{KafkaConnector, connector::get::topic_config_from_entries, resource::KafkaTopic};
+
+/// Topic state for one cluster, fetched with one metadata request and batched DescribeConfigs
+pub struct KafkaClusterCache {
+    fetched_at: Instant,
+    topics: HashMap<String, KafkaTopic>,
+    /// Topics that exist but whose configs weren't returned. These are errors rather than
+    /// empty configs, which would plan as removing every config override.
+    undescribed: HashSet<String>,
+}
+
+impl KafkaClusterCache {
+    fn topic(&self, cluster: &str, topic: &str) -> anyhow::Result<Option<KafkaTopic>> {
+        if self.undescribed.contains(topic) {
+            bail!("failed to describe_configs for topic {} in cluster {}", topic, cluster);
+        }
+        Ok(self.topics.get(topic).cloned())
+    }
+}
+
+pub type KafkaClusterCacheSlot = Arc<Mutex<Option<KafkaClusterCache>>>;
+
+/// Build a topic from its metadata, with an empty config
+pub fn topic_from_metadata(topic_metadata: &MetadataTopic) -> KafkaTopic {
+    // Get partition count
+    let partitions = topic_metadata.partitions().len() as i32;
+
+    // Get replication factor from first partition
+    let replication_factor = topic_metadata
+        .partitions()
+        .first()
+        .map(|p| p.replicas().len() as i16)
+        .unwrap_or(1);
+
+    KafkaTopic {
+        partitions,
+        replication_factor,
+        ..Default::default()
+    }
+}
+
+impl KafkaConnector {
+    /// Look up a topic through the cluster's cache, refreshing the whole cluster in bulk
+    /// if the cache is empty or older than `cache_ttl_secs`.
+    /// Returns None for topics that don't exist.
+    pub async fn cached_topic(&self, cluster: &str, topic: &str) -> anyhow::Result<Option<KafkaTopic>> {
+        let ttl = Duration::from_secs(self.config.read().await.cache_ttl_secs);
+
+        // Concurrent gets for the same cluster wait on the slot, so only one of them refreshes it
+        let slot = self.cache_slot(cluster).await;
+        let mut cache = slot.lock().await;
+
+        if let Some(cached) = cache.as_ref()
+            && cached.fetched_at.elapsed() < ttl
+        {
+            return cached.topic(cluster, topic);
+        }
+
+        let fetched = self.fetch_cluster_topics(cluster).await?;
+        let res = fetched.topic(cluster, topic);
+        *cache = Some(fetched);
+
+        res
+    }
+
+    /// Drop cached state for a cluster, e.g. after an op has changed it
+    pub async fn invalidate_cache(&self, cluster: &str) {
+        self.cache.lock().await.remove(cluster);
+    }
+
+    async fn cache_slot(&self, cluster: &str) -> KafkaClusterCacheSlot {
+        self.cache.lock().await.entry(cluster.to_string()).or_default().clone()
+    }
+
+    async fn fetch_cluster_topics(&self, cluster: &str) -> anyhow::Result<KafkaClusterCache> {
+        let metadata = self.fetch_metadata(cluster, None).await?;
+
+        let mut topics = HashMap::new();
+        for topic_metadata in metadata.topics() {
+            if topic_metadata.error().is_some() {
+                continue;
+            }
+            topics.insert(topic_metadata.name().to_string(), topic_from_metadata(topic_metadata));
+        }
+
+        let topic_names: Vec<String> = topics.keys().cloned().collect();
+        let mut configs = self.describe_topic_configs(cluster, &topic_names).await?;
+
+        let mut undescribed = HashSet::new();
+        for (topic_name, topic) in topics.iter_mut() {
+            if let Some(entries) = configs.remove(topic_name) {
+                topic.config = topic_config_from_entries(entries);
+            } else {
+                tracing::warn!("No config returned for topic '{}' in cluster '{}'", topic_name, cluster);
+                undescribed.insert(topic_name.clone());
+            }
+        }
+
+        Ok(KafkaClusterCache {
+            fetched_at: Instant::now(),
+            topics,
+            undescribed,
+        })
+    }
+}
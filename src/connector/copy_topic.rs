@@ -0,0 +1,390 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{Context, anyhow, bail};
+use autoschematic_core::{
+    connector::{ConnectorOp, ResourceAddress, TaskExecResponse},
+    util::RON,
+};
+use futures_util::future::try_join_all;
+use rdkafka::{
+    Offset, TopicPartitionList,
+    consumer::{CommitMode, Consumer},
+    error::KafkaError,
+    message::{Message, OwnedMessage},
+    producer::FutureRecord,
+    util::Timeout,
+};
+use rdkafka_autoschematic_fork as rdkafka;
+
+use crate::{
+    KafkaConnector,
+    addr::KafkaResourceAddress,
+    client::{KafkaConsumer, create_consumer, create_producer},
+    op::KafkaConnectorOp,
+    task::{KafkaCopyPartitionState, KafkaCopyTopicState, KafkaCopyTopicTask},
+};
+
+/// Records read from the source and produced to the target before progress is recorded
+const COPY_BATCH_SIZE: usize = 1000;
+
+/// Records copied per task_exec call; the task returns its progress as state after this many
+const COPY_STEP_RECORDS: u64 = 50_000;
+
+/// Read up to `max` records of a partition starting at `from`, stopping before `end`.
+/// Returns the records and the offset to resume from. Blocks on librdkafka.
+fn read_batch(
+    consumer: &KafkaConsumer,
+    topic: &str,
+    partition: i32,
+    from: i64,
+    end: i64,
+    max: usize,
+    timeout: Duration,
+) -> anyhow::Result<(Vec<OwnedMessage>, i64)> {
+    let mut tpl = TopicPartitionList::new();
+    tpl.add_partition_offset(topic, partition, Offset::Offset(from))?;
+    consumer.assign(&tpl)?;
+
+    let mut messages = Vec::new();
+    let mut next_offset = from;
+
+    while messages.len() < max && next_offset < end {
+        let message = match consumer.poll(timeout) {
+            None => bail!(
+                "Timed out reading partition {} of topic '{}' at offset {}",
+                partition,
+                topic,
+                next_offset
+            ),
+            // The rest of the range holds no records, e.g. compacted away or transaction markers
+            Some(Err(KafkaError::PartitionEOF(_))) => {
+                next_offset = end;
+                break;
+            }
+            Some(Err(e)) => return Err(e).with_context(|| format!("Failed to read topic '{}'", topic)),
+            Some(Ok(message)) => message,
+        };
+
+        if message.offset() >= end {
+            next_offset = end;
+            break;
+        }
+
+        next_offset = message.offset() + 1;
+        messages.push(message.detach());
+    }
+
+    consumer.unassign()?;
+    Ok((messages, next_offset))
+}
+
+async fn watermarks(
+    consumer: Arc<KafkaConsumer>,
+    topic: &str,
+    partition: i32,
+    timeout: Duration,
+) -> anyhow::Result<(i64, i64)> {
+    let topic = topic.to_string();
+    Ok(tokio::task::spawn_blocking(move || consumer.fetch_watermarks(&topic, partition, timeout)).await??)
+}
+
+async fn high_watermark(
+    consumer: Arc<KafkaConsumer>,
+    topic: &str,
+    partition: i32,
+    timeout: Duration,
+) -> anyhow::Result<i64> {
+    Ok(watermarks(consumer, topic, partition, timeout).await?.1)
+}
+
+impl KafkaConnector {
+    /// Run one step of a topic copy, returning the progress to resume from as the task's
+    /// next state, or no state once the copy and offset translation are complete.
+    pub async fn copy_topic(&self, task: &KafkaCopyTopicTask, state: Option<Vec<u8>>) -> anyhow::Result<TaskExecResponse> {
+        let target_topic = task.target_topic();
+
+        self.check_cluster_mode(&task.target_cluster, false).await?;
+
+        if self.config.read().await.validate_only {
+            bail!(
+                "validate_only is set in kafka/config.ron; not copying into topic '{}'",
+                target_topic
+            );
+        }
+
+        // The first step only records the starting point, so an interruption during the copy
+        // always has state to resume from
+        let mut state: KafkaCopyTopicState = match state {
+            Some(state) => RON.from_str(std::str::from_utf8(&state)?)?,
+            None => {
+                let state = self.start_copy(task).await?;
+                return Ok(TaskExecResponse {
+                    next_state: Some(RON.to_string(&state)?.into_bytes()),
+                    friendly_message: Some(format!(
+                        "Prepared topic '{}' in cluster '{}' to copy {} partition(s) from cluster '{}'",
+                        target_topic,
+                        task.target_cluster,
+                        state.partitions.len(),
+                        task.source_cluster
+                    )),
+                    ..Default::default()
+                });
+            }
+        };
+
+        let (source_config, timeout) = self.cluster_config(&task.source_cluster).await?;
+        let (target_config, _) = self.cluster_config(&task.target_cluster).await?;
+        let source = Arc::new(create_consumer(&self.prefix, &source_config, None)?);
+        let target = Arc::new(create_consumer(&self.prefix, &target_config, None)?);
+        let producer = &create_producer(&self.prefix, &target_config)?;
+
+        let mut step_records = 0;
+        for (partition, progress) in state.partitions.iter_mut() {
+            let partition = *partition;
+
+            if progress.is_done() {
+                continue;
+            }
+            if step_records >= COPY_STEP_RECORDS {
+                break;
+            }
+
+            // If the target is ahead of the recorded progress, the last batch was delivered
+            // but its progress wasn't saved, so those records are skipped rather than copied twice.
+            let target_high = high_watermark(target.clone(), target_topic, partition, timeout).await?;
+            let delivered = target_high - progress.target_next_offset;
+            if delivered < 0 {
+                bail!(
+                    "Partition {} of topic '{}' in cluster '{}' has fewer records than already copied; \
+                     was it modified during the copy?",
+                    partition,
+                    target_topic,
+                    task.target_cluster
+                );
+            }
+            let mut skip = delivered as usize;
+
+            while !progress.is_done() && step_records < COPY_STEP_RECORDS {
+                let batch_size = if skip > 0 { skip } else { COPY_BATCH_SIZE };
+
+                let (messages, next_offset) = {
+                    let source = source.clone();
+                    let topic = task.topic.clone();
+                    let (from, end) = (progress.next_offset, progress.end_offset);
+                    let _permit = self.permit().await?;
+                    tokio::task::spawn_blocking(move || {
+                        read_batch(&source, &topic, partition, from, end, batch_size, timeout)
+                    })
+                    .await??
+                };
+
+                if skip > 0 {
+                    skip = 0;
+                    tracing::info!(
+                        "Skipping {} records of partition {} already delivered to '{}'",
+                        messages.len(),
+                        partition,
+                        target_topic
+                    );
+                } else if !messages.is_empty() {
+                    let sends = messages.iter().map(|message| {
+                        let mut record = FutureRecord::<[u8], [u8]>::to(target_topic).partition(partition);
+                        if let Some(key) = message.key() {
+                            record = record.key(key);
+                        }
+                        if let Some(payload) = message.payload() {
+                            record = record.payload(payload);
+                        }
+                        if let Some(timestamp) = message.timestamp().to_millis() {
+                            record = record.timestamp(timestamp);
+                        }
+                        if let Some(headers) = message.headers() {
+                            record = record.headers(headers.clone());
+                        }
+
+                        async move {
+                            producer
+                                .send(record, Timeout::After(timeout))
+                                .await
+                                .map_err(|(e, _)| anyhow!("Failed to copy record at offset {}: {}", message.offset(), e))
+                        }
+                    });
+
+                    try_join_all(sends).await.with_context(|| {
+                        format!(
+                            "Failed to copy partition {} of topic '{}' to cluster '{}'",
+                            partition, task.topic, task.target_cluster
+                        )
+                    })?;
+
+                    progress.checkpoints.push((messages[0].offset(), progress.target_next_offset));
+                }
+
+                progress.next_offset = next_offset;
+                progress.target_next_offset += messages.len() as i64;
+                progress.copied += messages.len() as u64;
+                step_records += messages.len() as u64;
+            }
+        }
+
+        let copied: u64 = state.partitions.values().map(|p| p.copied).sum();
+
+        if state.partitions.values().any(|p| !p.is_done()) {
+            return Ok(TaskExecResponse {
+                next_state: Some(RON.to_string(&state)?.into_bytes()),
+                friendly_message: Some(format!(
+                    "Copied {} records of topic '{}' from cluster '{}' to '{}' so far",
+                    copied, task.topic, task.source_cluster, task.target_cluster
+                )),
+                ..Default::default()
+            });
+        }
+
+        let translated = self.translate_group_offsets(task, &state).await?;
+
+        Ok(TaskExecResponse {
+            friendly_message: Some(format!(
+                "Copied {} records of topic '{}' from cluster '{}' to topic '{}' in cluster '{}'{}",
+                copied,
+                task.topic,
+                task.source_cluster,
+                target_topic,
+                task.target_cluster,
+                if translated.is_empty() {
+                    String::new()
+                } else {
+                    format!("; translated offsets for consumer groups {}", translated.join(", "))
+                }
+            )),
+            ..Default::default()
+        })
+    }
+
+    /// Create the target topic if needed, and record the source watermarks the copy runs to
+    async fn start_copy(&self, task: &KafkaCopyTopicTask) -> anyhow::Result<KafkaCopyTopicState> {
+        let target_topic = task.target_topic();
+
+        let Some(definition) = self.fetch_topic(&task.source_cluster, &task.topic).await? else {
+            bail!("Topic '{}' not found in cluster '{}'", task.topic, task.source_cluster);
+        };
+
+        match self.fetch_topic(&task.target_cluster, target_topic).await? {
+            Some(existing) => {
+                if existing.partitions < definition.partitions {
+                    bail!(
+                        "Topic '{}' in cluster '{}' has {} partitions, fewer than the {} being copied",
+                        target_topic,
+                        task.target_cluster,
+                        existing.partitions,
+                        definition.partitions
+                    );
+                }
+            }
+            None => {
+                let topic_addr = KafkaResourceAddress::Topic {
+                    cluster: task.target_cluster.clone(),
+                    topic: target_topic.to_string(),
+                };
+                let op = KafkaConnectorOp::CreateTopic(definition.clone());
+                self.do_op_exec(&topic_addr.to_path_buf(), &op.to_string()?).await?;
+            }
+        }
+
+        let (source_config, timeout) = self.cluster_config(&task.source_cluster).await?;
+        let (target_config, _) = self.cluster_config(&task.target_cluster).await?;
+        let source = Arc::new(create_consumer(&self.prefix, &source_config, None)?);
+        let target = Arc::new(create_consumer(&self.prefix, &target_config, None)?);
+
+        let mut state = KafkaCopyTopicState::default();
+        for partition in 0..definition.partitions {
+            let (low, high) = watermarks(source.clone(), &task.topic, partition, timeout).await?;
+
+            // Records already in the target would end up interleaved with the copy
+            if high_watermark(target.clone(), target_topic, partition, timeout).await? > 0 {
+                bail!(
+                    "Topic '{}' in cluster '{}' already contains records; copy into an empty or new topic",
+                    target_topic,
+                    task.target_cluster
+                );
+            }
+
+            state.partitions.insert(
+                partition,
+                KafkaCopyPartitionState {
+                    next_offset: low,
+                    end_offset: high,
+                    ..Default::default()
+                },
+            );
+        }
+
+        Ok(state)
+    }
+
+    /// Commit each consumer group's source offsets, translated, on the target topic.
+    /// Returns the groups that had offsets to translate.
+    async fn translate_group_offsets(
+        &self,
+        task: &KafkaCopyTopicTask,
+        state: &KafkaCopyTopicState,
+    ) -> anyhow::Result<Vec<String>> {
+        let (source_config, timeout) = self.cluster_config(&task.source_cluster).await?;
+        let (target_config, _) = self.cluster_config(&task.target_cluster).await?;
+
+        let mut translated = Vec::new();
+        for group in &task.consumer_groups {
+            let prefix = self.prefix.clone();
+            let source_config = source_config.clone();
+            let target_config = target_config.clone();
+            let group_name = group.clone();
+            let topic = task.topic.clone();
+            let target_topic = task.target_topic().to_string();
+            let state = state.clone();
+
+            let committed = tokio::task::spawn_blocking(move || {
+                let source = create_consumer(&prefix, &source_config, Some(&group_name))?;
+                let mut tpl = TopicPartitionList::new();
+                for partition in state.partitions.keys() {
+                    tpl.add_partition(&topic, *partition);
+                }
+
+                let mut target_tpl = TopicPartitionList::new();
+                for elem in source.committed_offsets(tpl, timeout)?.elements() {
+                    if let (Offset::Offset(offset), Some(progress)) =
+                        (elem.offset(), state.partitions.get(&elem.partition()))
+                    {
+                        target_tpl.add_partition_offset(
+                            &target_topic,
+                            elem.partition(),
+                            Offset::Offset(progress.translate_offset(offset)),
+                        )?;
+                    }
+                }
+
+                if target_tpl.count() == 0 {
+                    return Ok(false);
+                }
+
+                let target = create_consumer(&prefix, &target_config, Some(&group_name))?;
+                target
+                    .commit(&target_tpl, CommitMode::Sync)
+                    .with_context(|| format!("Failed to commit translated offsets for group '{}'", group_name))?;
+                Ok::<_, anyhow::Error>(true)
+            })
+            .await??;
+
+            if committed {
+                translated.push(group.clone());
+            } else {
+                tracing::warn!(
+                    "Consumer group '{}' has no committed offsets on topic '{}' in cluster '{}'",
+                    group,
+                    task.topic,
+                    task.source_cluster
+                );
+            }
+        }
+
+        Ok(translated)
+    }
+}
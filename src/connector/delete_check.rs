@@ -0,0 +1,266 @@
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::bail;
+use rdkafka::{Offset, TopicPartitionList, consumer::Consumer};
+use rdkafka_autoschematic_fork as rdkafka;
+
+use crate::{
+    KafkaConnector,
+    client::{KafkaAdminClient, create_consumer},
+    config::KafkaClusterConfig,
+    group_offsets,
+};
+
+/// Signs that a topic is still in use, gathered before deleting it
+#[derive(Debug, Default)]
+pub struct KafkaTopicUsage {
+    /// Consumer groups with committed offsets on the topic
+    pub committed_groups: Vec<String>,
+    /// Consumer groups with active members assigned partitions of the topic
+    pub active_groups: Vec<String>,
+    /// Partitions that received records within the check window
+    pub recently_written_partitions: Vec<i32>,
+}
+
+impl KafkaTopicUsage {
+    pub fn is_empty(&self) -> bool {
+        self.committed_groups.is_empty() && self.active_groups.is_empty() && self.recently_written_partitions.is_empty()
+    }
+
+    pub fn report(&self, window_minutes: u64) -> String {
+        let mut lines = Vec::new();
+        if !self.active_groups.is_empty() {
+            lines.push(format!(
+                "consumer groups with active members: {}",
+                self.active_groups.join(", ")
+            ));
+        }
+        if !self.committed_groups.is_empty() {
+            lines.push(format!(
+                "consumer groups with committed offsets: {}",
+                self.committed_groups.join(", ")
+            ));
+        }
+        if !self.recently_written_partitions.is_empty() {
+            let partitions: Vec<String> = self.recently_written_partitions.iter().map(|p| p.to_string()).collect();
+            lines.push(format!(
+                "partitions written in the last {} minutes: {}",
+                window_minutes,
+                partitions.join(", ")
+            ));
+        }
+        lines.join("; ")
+    }
+}
+
+/// Read the topic names out of a member assignment in the consumer protocol's wire format:
+/// version (i16), then an array (i32 count) of topic (i16-length string) with an i32 array
+/// of partitions, then user data. Parsing stops at the first malformed field.
+fn assigned_topics(assignment: &[u8]) -> Vec<String> {
+    fn take<'a>(buf: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+        if buf.len() < n {
+            return None;
+        }
+        let (head, tail) = buf.split_at(n);
+        *buf = tail;
+        Some(head)
+    }
+
+    let mut buf = assignment;
+    let mut topics = Vec::new();
+
+    let _version = take(&mut buf, 2);
+    let Some(count) = take(&mut buf, 4).map(|b| i32::from_be_bytes([b[0], b[1], b[2], b[3]])) else {
+        return topics;
+    };
+
+    for _ in 0..count.max(0) {
+        let Some(len) = take(&mut buf, 2).map(|b| i16::from_be_bytes([b[0], b[1]])) else {
+            break;
+        };
+        let Some(name) = take(&mut buf, len.max(0) as usize) else {
+            break;
+        };
+        topics.push(String::from_utf8_lossy(name).into_owned());
+
+        let Some(partitions) = take(&mut buf, 4).map(|b| i32::from_be_bytes([b[0], b[1], b[2], b[3]])) else {
+            break;
+        };
+        if take(&mut buf, partitions.max(0) as usize * 4).is_none() {
+            break;
+        }
+    }
+
+    topics
+}
+
+/// Gather consumer group and recent-write activity for a topic. Blocks on librdkafka.
+fn inspect_topic_usage(
+    prefix: &Path,
+    client: &KafkaAdminClient,
+    cluster_config: &KafkaClusterConfig,
+    topic: &str,
+    window_minutes: u64,
+    timeout: Duration,
+) -> anyhow::Result<KafkaTopicUsage> {
+    let mut usage = KafkaTopicUsage::default();
+
+    let metadata = client.inner().fetch_metadata(Some(topic), timeout)?;
+    let partitions: Vec<i32> = metadata
+        .topics()
+        .first()
+        .map(|t| t.partitions().iter().map(|p| p.id()).collect())
+        .unwrap_or_default();
+
+    if window_minutes > 0 {
+        // The admin client can't look up offsets by time, so this takes a consumer of its own
+        let consumer = create_consumer(prefix, cluster_config, None)?;
+        let now_ms = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as i64;
+        let since_ms = now_ms - window_minutes as i64 * 60 * 1000;
+
+        let mut tpl = TopicPartitionList::new();
+        for partition in &partitions {
+            tpl.add_partition_offset(topic, *partition, Offset::Offset(since_ms))?;
+        }
+
+        // Partitions with no record at or after `since_ms` come back as Offset::End
+        for elem in consumer.offsets_for_times(tpl, timeout)?.elements() {
+            if let Offset::Offset(_) = elem.offset() {
+                usage.recently_written_partitions.push(elem.partition());
+            }
+        }
+    }
+
+    let groups = client.inner().fetch_group_list(None, timeout)?;
+    for group in groups.groups() {
+        if !(group.protocol_type() == "consumer" || group.protocol_type().is_empty()) {
+            continue;
+        }
+
+        let active = group.members().iter().any(|member| {
+            member
+                .assignment()
+                .is_some_and(|assignment| assigned_topics(assignment).iter().any(|t| t == topic))
+        });
+        if active {
+            usage.active_groups.push(group.name().to_string());
+        }
+
+        if group_offsets::has_committed_offsets(client, group.name(), topic, &partitions, timeout)? {
+            usage.committed_groups.push(group.name().to_string());
+        }
+    }
+
+    usage.active_groups.sort();
+    usage.committed_groups.sort();
+    Ok(usage)
+}
+
+impl KafkaConnector {
+    /// Fail unless a topic has no consumer groups reading it and no recent writes, so that
+    /// deleting it won't pull data out from under a live application.
+    pub async fn check_topic_unused(&self, cluster: &str, topic: &str) -> anyhow::Result<()> {
+        let client = self.client(cluster).await?;
+        let (cluster_config, timeout) = self.cluster_config(cluster).await?;
+        let window_minutes = self.config.read().await.delete_check_window_minutes;
+
+        let _permit = self.permit().await?;
+
+        let prefix = self.prefix.clone();
+        let topic_name = topic.to_string();
+        let usage = tokio::task::spawn_blocking(move || {
+            inspect_topic_usage(&prefix, &client, &cluster_config, &topic_name, window_minutes, timeout)
+        })
+        .await??;
+
+        if !usage.is_empty() {
+            bail!(
+                "Refusing to delete topic '{}' in cluster '{}', it still looks in use: {}. \
+                 Set lifecycle.force_delete on the topic and apply that before deleting it anyway.",
+                topic,
+                cluster,
+                usage.report(window_minutes)
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An assignment in the consumer protocol's wire format, version 0
+    fn assignment(topics: &[(&str, &[i32])], user_data: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(0i16.to_be_bytes());
+        buf.extend((topics.len() as i32).to_be_bytes());
+        for (topic, partitions) in topics {
+            buf.extend((topic.len() as i16).to_be_bytes());
+            buf.extend(topic.as_bytes());
+            buf.extend((partitions.len() as i32).to_be_bytes());
+            for partition in *partitions {
+                buf.extend(partition.to_be_bytes());
+            }
+        }
+        buf.extend((user_data.len() as i32).to_be_bytes());
+        buf.extend(user_data);
+        buf
+    }
+
+    #[test]
+    fn reads_assigned_topics() {
+        let buf = assignment(&[("orders", &[0, 1, 2]), ("payments", &[])], &[]);
+        assert_eq!(assigned_topics(&buf), vec!["orders", "payments"]);
+    }
+
+    #[test]
+    fn ignores_trailing_user_data() {
+        let buf = assignment(&[("orders", &[0])], b"\x00\x06sticky");
+        assert_eq!(assigned_topics(&buf), vec!["orders"]);
+    }
+
+    #[test]
+    fn stops_at_truncated_fields() {
+        let buf = assignment(&[("orders", &[0, 1]), ("payments", &[0])], &[]);
+
+        // Cut inside the second topic's name
+        let cut = 2 + 4 + (2 + 6 + 4 + 8) + 2 + 3;
+        assert_eq!(assigned_topics(&buf[..cut]), vec!["orders"]);
+
+        // Cut inside the first topic's partitions
+        assert_eq!(assigned_topics(&buf[..2 + 4 + 2 + 6 + 4 + 2]), vec!["orders"]);
+
+        assert!(assigned_topics(&buf[..3]).is_empty());
+        assert!(assigned_topics(&[]).is_empty());
+    }
+
+    #[test]
+    fn treats_negative_counts_as_empty() {
+        let mut buf = Vec::new();
+        buf.extend(0i16.to_be_bytes());
+        buf.extend((-1i32).to_be_bytes());
+        assert!(assigned_topics(&buf).is_empty());
+
+        // A negative partition count reads as no partitions, so the next topic still parses
+        let mut buf = Vec::new();
+        buf.extend(0i16.to_be_bytes());
+        buf.extend(2i32.to_be_bytes());
+        for topic in ["orders", "payments"] {
+            buf.extend((topic.len() as i16).to_be_bytes());
+            buf.extend(topic.as_bytes());
+            buf.extend((-1i32).to_be_bytes());
+        }
+        assert_eq!(assigned_topics(&buf), vec!["orders", "payments"]);
+
+        // A negative name length reads as an empty name
+        let mut buf = Vec::new();
+        buf.extend(0i16.to_be_bytes());
+        buf.extend(1i32.to_be_bytes());
+        buf.extend((-1i16).to_be_bytes());
+        buf.extend(0i32.to_be_bytes());
+        assert_eq!(assigned_topics(&buf), vec![""]);
+    }
+}
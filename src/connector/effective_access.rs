@@ -0,0 +1,451 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    KafkaConnector,
+    acl::KafkaAclFilter,
+    resource::{
+        CLUSTER_RESOURCE_NAME, KafkaAcl, KafkaAclOperation, KafkaAclPermission, KafkaPrincipal, KafkaPrincipalType,
+        KafkaResourcePatternType, KafkaResourceType,
+    },
+    task::KafkaEffectiveAccessTask,
+};
+
+const TOPIC_OPERATIONS: [KafkaAclOperation; 8] = [
+    KafkaAclOperation::Read,
+    KafkaAclOperation::Write,
+    KafkaAclOperation::Create,
+    KafkaAclOperation::Delete,
+    KafkaAclOperation::Alter,
+    KafkaAclOperation::Describe,
+    KafkaAclOperation::DescribeConfigs,
+    KafkaAclOperation::AlterConfigs,
+];
+
+const GROUP_OPERATIONS: [KafkaAclOperation; 3] = [
+    KafkaAclOperation::Read,
+    KafkaAclOperation::Describe,
+    KafkaAclOperation::Delete,
+];
+
+const CLUSTER_OPERATIONS: [KafkaAclOperation; 7] = [
+    KafkaAclOperation::Create,
+    KafkaAclOperation::Alter,
+    KafkaAclOperation::Describe,
+    KafkaAclOperation::ClusterAction,
+    KafkaAclOperation::DescribeConfigs,
+    KafkaAclOperation::AlterConfigs,
+    KafkaAclOperation::IdempotentWrite,
+];
+
+/// The outcome of one operation on one resource, with the binding that decided it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum KafkaAccessDecision {
+    /// A binding allows the operation and none denies it
+    Allowed(KafkaAcl),
+    /// A binding denies the operation, whatever else allows it
+    Denied(KafkaAcl),
+    /// With no host given, a binding allows the operation but one limited to a host denies it,
+    /// so the outcome depends on where the client connects from
+    DeniedFromHost { allow: KafkaAcl, deny: KafkaAcl },
+    /// No binding allows the operation, so the broker refuses it
+    NotAllowed,
+}
+
+/// What a principal may do, by resource and operation
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct KafkaEffectiveAccessReport {
+    pub cluster: String,
+    pub principal: String,
+    pub host: Option<String>,
+    pub topics: BTreeMap<String, BTreeMap<KafkaAclOperation, KafkaAccessDecision>>,
+    pub groups: BTreeMap<String, BTreeMap<KafkaAclOperation, KafkaAccessDecision>>,
+    pub cluster_operations: BTreeMap<KafkaAclOperation, KafkaAccessDecision>,
+}
+
+impl KafkaEffectiveAccessReport {
+    /// One line per resource and operation, naming the deciding binding
+    pub fn render(&self) -> String {
+        let mut lines = vec![format!(
+            "Effective access for {} in cluster '{}' from {}",
+            self.principal,
+            self.cluster,
+            self.host
+                .as_deref()
+                .map(|h| format!("host {}", h))
+                .unwrap_or_else(|| String::from("any host"))
+        )];
+
+        let mut section = |heading: String, decisions: &BTreeMap<KafkaAclOperation, KafkaAccessDecision>| {
+            lines.push(heading);
+            for (operation, decision) in decisions {
+                let outcome = match decision {
+                    KafkaAccessDecision::Allowed(acl) => format!("allowed by {}", acl.describe()),
+                    KafkaAccessDecision::Denied(acl) => format!("DENIED by {}", acl.describe()),
+                    KafkaAccessDecision::DeniedFromHost { allow, deny } => format!(
+                        "allowed by {}, but DENIED from host {} by {}",
+                        allow.describe(),
+                        deny.host,
+                        deny.describe()
+                    ),
+                    KafkaAccessDecision::NotAllowed => String::from("denied, no binding allows it"),
+                };
+                lines.push(format!("  {:<16} {}", format!("{:?}", operation), outcome));
+            }
+        };
+
+        for (topic, decisions) in &self.topics {
+            section(format!("topic '{}'", topic), decisions);
+        }
+        for (group, decisions) in &self.groups {
+            section(format!("group '{}'", group), decisions);
+        }
+        section(String::from("cluster"), &self.cluster_operations);
+
+        lines.join("\n")
+    }
+}
+
+/// Whether a binding is for this principal, directly or through the `User:*` wildcard,
+/// and for this host. When the host isn't known, deny bindings limited to a host are kept,
+/// since they may apply, while allow bindings limited to a host are not.
+fn applies_to(acl: &KafkaAcl, principal: &KafkaPrincipal, host: Option<&str>) -> bool {
+    let principal_matches = acl.principal == *principal
+        || (acl.principal.principal_type == KafkaPrincipalType::User && acl.principal.is_wildcard());
+
+    let host_matches = match host {
+        Some(host) => acl.host == "*" || acl.host == host,
+        None => acl.host == "*" || acl.permission == KafkaAclPermission::Deny,
+    };
+
+    principal_matches && host_matches
+}
+
+fn matches_resource(acl: &KafkaAcl, resource_type: &KafkaResourceType, name: &str) -> bool {
+    if acl.resource_type != *resource_type {
+        return false;
+    }
+
+    match acl.pattern_type {
+        KafkaResourcePatternType::Literal => acl.resource_name == name || acl.resource_name == "*",
+        KafkaResourcePatternType::Prefixed => name.starts_with(&acl.resource_name),
+        KafkaResourcePatternType::Match | KafkaResourcePatternType::Any => false,
+    }
+}
+
+/// Whether a binding's operation covers the requested one. As in the broker, allowing Read,
+/// Write, Delete or Alter also allows Describe, and allowing AlterConfigs also allows
+/// DescribeConfigs, while a deny binding only covers its own operation or All.
+fn covers(acl: &KafkaAcl, operation: &KafkaAclOperation) -> bool {
+    if acl.operation == *operation || acl.operation == KafkaAclOperation::All {
+        return true;
+    }
+
+    acl.permission == KafkaAclPermission::Allow
+        && match operation {
+            KafkaAclOperation::Describe => matches!(
+                acl.operation,
+                KafkaAclOperation::Read | KafkaAclOperation::Write | KafkaAclOperation::Delete | KafkaAclOperation::Alter
+            ),
+            KafkaAclOperation::DescribeConfigs => acl.operation == KafkaAclOperation::AlterConfigs,
+            _ => false,
+        }
+}
+
+/// Literal names rank above prefixes, longer prefixes above shorter ones, and `*` last
+fn specificity(acl: &KafkaAcl) -> (u8, usize) {
+    match acl.pattern_type {
+        KafkaResourcePatternType::Literal if acl.resource_name == "*" => (0, 0),
+        KafkaResourcePatternType::Literal => (2, acl.resource_name.len()),
+        _ => (1, acl.resource_name.len()),
+    }
+}
+
+/// Decide one operation from the bindings that apply to the principal from `host`. When
+/// several bindings decide it, the most specific is reported.
+fn decide(
+    bindings: &[KafkaAcl],
+    host: Option<&str>,
+    resource_type: &KafkaResourceType,
+    name: &str,
+    operation: &KafkaAclOperation,
+) -> KafkaAccessDecision {
+    let deciding = |permission: KafkaAclPermission, host_limited: bool| {
+        bindings
+            .iter()
+            .filter(|acl| acl.permission == permission && (host.is_none() && acl.host != "*") == host_limited)
+            .filter(|acl| matches_resource(acl, resource_type, name) && covers(acl, operation))
+            .max_by_key(|acl| specificity(acl))
+            .cloned()
+    };
+
+    if let Some(acl) = deciding(KafkaAclPermission::Deny, false) {
+        KafkaAccessDecision::Denied(acl)
+    } else if let Some(allow) = deciding(KafkaAclPermission::Allow, false) {
+        match deciding(KafkaAclPermission::Deny, true) {
+            Some(deny) => KafkaAccessDecision::DeniedFromHost { allow, deny },
+            None => KafkaAccessDecision::Allowed(allow),
+        }
+    } else {
+        KafkaAccessDecision::NotAllowed
+    }
+}
+
+fn decide_all(
+    bindings: &[KafkaAcl],
+    host: Option<&str>,
+    resource_type: &KafkaResourceType,
+    name: &str,
+    operations: &[KafkaAclOperation],
+) -> BTreeMap<KafkaAclOperation, KafkaAccessDecision> {
+    operations
+        .iter()
+        .map(|operation| (operation.clone(), decide(bindings, host, resource_type, name, operation)))
+        .collect()
+}
+
+impl KafkaConnector {
+    /// Evaluate every ACL binding in a cluster for one principal, over the task's topics and
+    /// groups or, if it names none, every topic and group in the cluster
+    pub async fn effective_access(&self, task: &KafkaEffectiveAccessTask) -> anyhow::Result<KafkaEffectiveAccessReport> {
+        let cluster = &task.cluster;
+        let principal = KafkaPrincipal::from_wire(&task.principal)?;
+
+        let host = task.host.as_deref();
+
+        let bindings: Vec<KafkaAcl> = self
+            .describe_acls(cluster, KafkaAclFilter::default())
+            .await?
+            .into_iter()
+            .filter(|acl| applies_to(acl, &principal, host))
+            .collect();
+
+        let topics = if task.topics.is_empty() {
+            let metadata = self.fetch_metadata(cluster, None).await?;
+            metadata.topics().iter().map(|t| t.name().to_string()).collect()
+        } else {
+            task.topics.clone()
+        };
+
+        let groups = if task.groups.is_empty() {
+            self.list_groups(cluster).await?
+        } else {
+            task.groups.clone()
+        };
+
+        Ok(KafkaEffectiveAccessReport {
+            cluster: cluster.clone(),
+            principal: principal.to_wire(),
+            host: task.host.clone(),
+            topics: topics
+                .into_iter()
+                .map(|topic| {
+                    let decisions = decide_all(&bindings, host, &KafkaResourceType::Topic, &topic, &TOPIC_OPERATIONS);
+                    (topic, decisions)
+                })
+                .collect(),
+            groups: groups
+                .into_iter()
+                .map(|group| {
+                    let decisions = decide_all(&bindings, host, &KafkaResourceType::Group, &group, &GROUP_OPERATIONS);
+                    (group, decisions)
+                })
+                .collect(),
+            cluster_operations: decide_all(
+                &bindings,
+                host,
+                &KafkaResourceType::Cluster,
+                CLUSTER_RESOURCE_NAME,
+                &CLUSTER_OPERATIONS,
+            ),
+        })
+    }
+
+    /// Run the effective access task, writing the report as RON if the task asks for it
+    pub async fn effective_access_task(&self, task: &KafkaEffectiveAccessTask) -> anyhow::Result<String> {
+        let report = self.effective_access(task).await?;
+
+        if let Some(report_path) = &task.report_path {
+            self.write_report(report_path, &report)?;
+        }
+
+        Ok(report.render())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binding(
+        permission: KafkaAclPermission,
+        operation: KafkaAclOperation,
+        pattern_type: KafkaResourcePatternType,
+        resource_name: &str,
+    ) -> KafkaAcl {
+        KafkaAcl {
+            resource_name: resource_name.to_string(),
+            pattern_type,
+            principal: KafkaPrincipal::user("alice"),
+            operation,
+            permission,
+            ..Default::default()
+        }
+    }
+
+    fn decide_topic(bindings: &[KafkaAcl], host: Option<&str>, operation: KafkaAclOperation) -> KafkaAccessDecision {
+        decide(bindings, host, &KafkaResourceType::Topic, "orders", &operation)
+    }
+
+    #[test]
+    fn deny_overrides_a_more_specific_allow() {
+        let allow = binding(
+            KafkaAclPermission::Allow,
+            KafkaAclOperation::Read,
+            KafkaResourcePatternType::Literal,
+            "orders",
+        );
+        let deny = binding(
+            KafkaAclPermission::Deny,
+            KafkaAclOperation::All,
+            KafkaResourcePatternType::Literal,
+            "*",
+        );
+
+        assert_eq!(
+            decide_topic(&[allow.clone(), deny.clone()], None, KafkaAclOperation::Read),
+            KafkaAccessDecision::Denied(deny)
+        );
+        assert_eq!(
+            decide_topic(&[allow.clone()], None, KafkaAclOperation::Read),
+            KafkaAccessDecision::Allowed(allow)
+        );
+    }
+
+    #[test]
+    fn reports_the_most_specific_allow() {
+        let wildcard = binding(
+            KafkaAclPermission::Allow,
+            KafkaAclOperation::Write,
+            KafkaResourcePatternType::Literal,
+            "*",
+        );
+        let prefix = binding(
+            KafkaAclPermission::Allow,
+            KafkaAclOperation::Write,
+            KafkaResourcePatternType::Prefixed,
+            "ord",
+        );
+
+        assert_eq!(
+            decide_topic(&[wildcard, prefix.clone()], None, KafkaAclOperation::Write),
+            KafkaAccessDecision::Allowed(prefix)
+        );
+    }
+
+    #[test]
+    fn allow_implies_describe() {
+        for operation in [
+            KafkaAclOperation::Read,
+            KafkaAclOperation::Write,
+            KafkaAclOperation::Delete,
+            KafkaAclOperation::Alter,
+        ] {
+            let allow = binding(
+                KafkaAclPermission::Allow,
+                operation,
+                KafkaResourcePatternType::Literal,
+                "orders",
+            );
+            assert_eq!(
+                decide_topic(&[allow.clone()], None, KafkaAclOperation::Describe),
+                KafkaAccessDecision::Allowed(allow)
+            );
+        }
+
+        let alter_configs = binding(
+            KafkaAclPermission::Allow,
+            KafkaAclOperation::AlterConfigs,
+            KafkaResourcePatternType::Literal,
+            "orders",
+        );
+        assert_eq!(
+            decide_topic(&[alter_configs.clone()], None, KafkaAclOperation::DescribeConfigs),
+            KafkaAccessDecision::Allowed(alter_configs)
+        );
+
+        let create = binding(
+            KafkaAclPermission::Allow,
+            KafkaAclOperation::Create,
+            KafkaResourcePatternType::Literal,
+            "orders",
+        );
+        assert_eq!(
+            decide_topic(&[create], None, KafkaAclOperation::Describe),
+            KafkaAccessDecision::NotAllowed
+        );
+    }
+
+    #[test]
+    fn deny_does_not_imply_describe() {
+        let allow = binding(
+            KafkaAclPermission::Allow,
+            KafkaAclOperation::Describe,
+            KafkaResourcePatternType::Literal,
+            "orders",
+        );
+        let deny = binding(
+            KafkaAclPermission::Deny,
+            KafkaAclOperation::Write,
+            KafkaResourcePatternType::Literal,
+            "orders",
+        );
+
+        assert_eq!(
+            decide_topic(&[allow.clone(), deny], None, KafkaAclOperation::Describe),
+            KafkaAccessDecision::Allowed(allow)
+        );
+    }
+
+    #[test]
+    fn host_limited_deny_is_conditional_without_a_host() {
+        let allow = binding(
+            KafkaAclPermission::Allow,
+            KafkaAclOperation::Read,
+            KafkaResourcePatternType::Literal,
+            "orders",
+        );
+        let deny = KafkaAcl {
+            host: String::from("10.0.0.1"),
+            ..binding(
+                KafkaAclPermission::Deny,
+                KafkaAclOperation::Read,
+                KafkaResourcePatternType::Literal,
+                "orders",
+            )
+        };
+        let host_limited_allow = KafkaAcl {
+            host: deny.host.clone(),
+            ..allow.clone()
+        };
+        let principal = KafkaPrincipal::user("alice");
+
+        assert!(applies_to(&deny, &principal, None));
+        assert!(!applies_to(&deny, &principal, Some("10.0.0.2")));
+        assert!(!applies_to(&host_limited_allow, &principal, None));
+
+        let bindings = [allow.clone(), deny.clone()];
+        assert_eq!(
+            decide_topic(&bindings, None, KafkaAclOperation::Read),
+            KafkaAccessDecision::DeniedFromHost {
+                allow,
+                deny: deny.clone()
+            }
+        );
+        assert_eq!(
+            decide_topic(&bindings, Some("10.0.0.1"), KafkaAclOperation::Read),
+            KafkaAccessDecision::Denied(deny)
+        );
+    }
+}
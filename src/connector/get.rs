@@ -1,102 +1,123 @@
-use crate::{KafkaConnector, addr::KafkaResourceAddress, client::get_operation_timeout, resource};
-use anyhow::{Context, anyhow, bail};
+use crate::{
+    KafkaConnector, acl::KafkaAclFilter, acl_state, addr::KafkaResourceAddress, config::KafkaConnectorConfig,
+    connector::cache::topic_from_metadata, lifecycle, profile, resource, shared_state::KafkaSharedTopicState,
+};
+use anyhow::bail;
 use autoschematic_core::{
     connector::{GetResourceResponse, Resource, ResourceAddress},
     get_resource_response,
+    util::RON,
 };
+use futures_util::future::try_join_all;
 use indexmap::IndexMap;
-use rdkafka::admin::AdminOptions;
+use rdkafka::admin::ConfigEntry;
 use rdkafka_autoschematic_fork as rdkafka;
-use std::collections::HashMap;
-use std::path::Path;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
 
 impl KafkaConnector {
     pub async fn do_get(&self, addr: &Path) -> anyhow::Result<Option<GetResourceResponse>> {
         let addr = KafkaResourceAddress::from_path(addr)?;
 
         match addr {
-            KafkaResourceAddress::Config => Ok(None),
-            KafkaResourceAddress::Topic { cluster, topic } => {
-                let clients = self.clients.read().await;
-                let config = self.config.read().await;
-                let timeout = get_operation_timeout(config.operation_timeout_ms);
-
-                let topic_specifier = rdkafka::admin::ResourceSpecifier::Topic(&topic);
-                let opts = AdminOptions::new().operation_timeout(Some(timeout));
-
-                let client = clients
-                    .get(&cluster)
-                    .ok_or_else(|| anyhow!("Cluster '{}' not found in configuration", cluster))?;
-
-                let config = client.describe_configs([&topic_specifier], &opts).await?;
-
-                let Some(config) = config.first() else { return Ok(None) };
-                // Fetch topic metadata
-                match client.inner().fetch_metadata(Some(&topic), timeout) {
-                    Ok(metadata) => {
-                        if let Some(topic_metadata) = metadata.topics().iter().find(|t| t.name() == topic) {
-                            if topic_metadata.error().is_some() {
-                                return Ok(None);
-                            }
-                            // Get partition count
-                            let partitions = topic_metadata.partitions().len() as i32;
-
-                            // Get replication factor from first partition
-                            let replication_factor = topic_metadata
-                                .partitions()
-                                .first()
-                                .map(|p| p.replicas().len() as i16)
-                                .unwrap_or(1);
-
-                            match config {
-                                Ok(config) => {
-                                    let mut config_map = IndexMap::new();
-
-                                    let mut entries = config.entries.clone();
-
-                                    entries.sort_by(|a, b| a.name.cmp(&b.name));
-
-                                    for entry in entries {
-                                        if entry.is_read_only {
-                                            continue;
-                                        }
-                                        if entry.is_sensitive {
-                                            continue;
-                                        }
-                                        if let Some(ref value) = entry.value {
-                                            config_map.insert(entry.name.to_owned(), value.to_owned());
-                                        }
-                                    }
-
-                                    // from_iter(config.entries.iter().map(|e| (e.name, e.value)));
-
-                                    let topic_resource = resource::KafkaTopic {
-                                        partitions,
-                                        replication_factor,
-                                        config: config_map,
-                                    };
-
-                                    get_resource_response!(resource::KafkaResource::Topic(topic_resource))
-                                }
-                                Err(e) => {
-                                    bail!("failed to describe_configs for topic {}: {:?}", topic, e);
-                                }
-                            }
-                        } else {
-                            Ok(None) // Topic doesn't exist
-                        }
-                    }
-                    Err(e) => {
-                        tracing::debug!("{e:?}");
-                        Ok(None)
-                    }
+            KafkaResourceAddress::Config | KafkaResourceAddress::Profile { .. } => Ok(None),
+            KafkaResourceAddress::Topic { ref cluster, ref topic } => match self.get_topic(cluster, topic).await? {
+                Some(mut topic_resource) => {
+                    topic_resource.lifecycle = lifecycle::recorded(&self.prefix, &addr, None)?;
+                    let spec = profile::describe_topic(&self.prefix, cluster, topic_resource)?;
+                    get_resource_response!(resource::KafkaResource::Topic(spec))
+                }
+                None => Ok(None),
+            },
+            KafkaResourceAddress::SharedTopic { ref topic } => {
+                // The definition covers the clusters it has been applied to, plus those it lists
+                // now; a same-named topic on any other cluster isn't part of it
+                let mut covered = KafkaSharedTopicState::applied(&self.prefix, topic)?;
+                let path = self.prefix.join(addr.to_path_buf());
+                if path.exists()
+                    && let Ok(resource::KafkaResource::SharedTopic(shared)) =
+                        resource::KafkaResource::from_bytes(&addr, &std::fs::read(&path)?)
+                {
+                    covered.extend(shared.clusters.into_keys());
+                }
+
+                let clusters: Vec<String> = self
+                    .shared_topic_clusters(topic)
+                    .await?
+                    .into_iter()
+                    .filter(|cluster| covered.contains(cluster))
+                    .collect();
+
+                let topics = try_join_all(clusters.iter().map(|cluster| self.get_topic(cluster, topic))).await?;
+
+                let mut topics: BTreeMap<String, resource::KafkaTopic> = clusters
+                    .into_iter()
+                    .zip(topics)
+                    .filter_map(|(cluster, topic)| Some((cluster, topic?)))
+                    .collect();
+
+                for (cluster, topic_resource) in topics.iter_mut() {
+                    topic_resource.lifecycle = lifecycle::recorded(&self.prefix, &addr, Some(cluster))?;
+                }
+
+                if topics.is_empty() {
+                    return Ok(None);
                 }
+
+                get_resource_response!(resource::KafkaResource::SharedTopic(
+                    resource::KafkaSharedTopic::from_clusters(&topics)
+                ))
             }
-            KafkaResourceAddress::Acl { cluster, acl_id } => {
-                // TODO: Implement ACL fetching when rdkafka supports it
-                // For now, return None
-                tracing::warn!("ACL fetching not yet implemented for cluster '{}', ACL '{}'", cluster, acl_id);
-                Ok(None)
+            KafkaResourceAddress::Acl { ref cluster, .. } => {
+                // The file's bindings are only those it has created and that still exist
+                let owned = acl_state::owned(&self.prefix, &addr)?;
+
+                let principals: BTreeSet<&resource::KafkaPrincipal> = owned.iter().map(|acl| &acl.principal).collect();
+                let live = try_join_all(
+                    principals
+                        .into_iter()
+                        .map(|principal| self.describe_acls(cluster, KafkaAclFilter::principal(principal))),
+                )
+                .await?;
+
+                let bindings: Vec<resource::KafkaAcl> =
+                    live.into_iter().flatten().filter(|acl| owned.contains(acl)).collect();
+                if bindings.is_empty() {
+                    return Ok(None);
+                }
+
+                get_resource_response!(resource::KafkaResource::Acl(resource::KafkaAclSet { bindings }))
+            }
+            KafkaResourceAddress::Access {
+                ref cluster,
+                ref principal,
+            } => {
+                let principal = resource::KafkaPrincipal::from_file_stem(principal)?;
+                let live: BTreeSet<resource::KafkaAcl> = self
+                    .describe_acls(cluster, KafkaAclFilter::principal(&principal))
+                    .await?
+                    .into_iter()
+                    .collect();
+
+                if live.is_empty() {
+                    return Ok(None);
+                }
+
+                // Describe the live bindings in terms of the access file, where one exists
+                let path = self.prefix.join(addr.to_path_buf());
+                let hint: Option<resource::KafkaAccess> = if path.exists() {
+                    RON.from_str(&std::fs::read_to_string(&path)?).ok()
+                } else {
+                    None
+                };
+
+                get_resource_response!(resource::KafkaResource::Access(resource::KafkaAccess::from_bindings(
+                    &principal,
+                    &live,
+                    hint.as_ref()
+                )))
             }
             KafkaResourceAddress::Quota { cluster, quota_id } => {
                 // TODO: Implement quota fetching when rdkafka supports it
@@ -110,4 +131,86 @@ impl KafkaConnector {
             }
         }
     }
+
+    /// A topic's current state in one cluster, without its lifecycle markers, which are
+    /// recorded in the outputs of whichever file defines it. Returns None if the topic doesn't exist.
+    pub async fn get_topic(&self, cluster: &str, topic: &str) -> anyhow::Result<Option<resource::KafkaTopic>> {
+        if self.config.read().await.cache_ttl_secs > 0 {
+            self.cached_topic(cluster, topic).await
+        } else {
+            self.fetch_topic(cluster, topic).await
+        }
+    }
+
+    /// Clusters a shared topic definition can apply to: every configured cluster, including those
+    /// only staged in kafka/config.ron, except those that manage the topic with their own file at
+    /// kafka/{cluster}/topics/{topic}.ron
+    pub async fn shared_topic_clusters(&self, topic: &str) -> anyhow::Result<Vec<String>> {
+        let mut configured: BTreeSet<String> = self.config.read().await.clusters.keys().cloned().collect();
+        if let Some(staged) = KafkaConnectorConfig::try_load(&self.prefix)? {
+            configured.extend(staged.clusters.into_keys());
+        }
+
+        Ok(configured
+            .into_iter()
+            .filter(|cluster| {
+                let addr = KafkaResourceAddress::Topic {
+                    cluster: cluster.to_string(),
+                    topic: topic.to_string(),
+                };
+                !self.prefix.join(addr.to_path_buf()).exists()
+            })
+            .collect())
+    }
+
+    /// Fetch a single topic's partitions, replication and config directly from the cluster,
+    /// bypassing the cache. Returns None if the topic doesn't exist.
+    pub async fn fetch_topic(&self, cluster: &str, topic: &str) -> anyhow::Result<Option<resource::KafkaTopic>> {
+        // Errors are returned rather than mapped to Ok(None):
+        // an unreachable cluster must not look like a deleted topic.
+        let metadata = self.fetch_metadata(cluster, Some(topic)).await?;
+
+        let Some(topic_metadata) = metadata.topics().iter().find(|t| t.name() == topic) else {
+            return Ok(None); // Topic doesn't exist
+        };
+
+        if topic_metadata.error().is_some() {
+            return Ok(None);
+        }
+
+        let mut configs = self.describe_topic_configs(cluster, &[topic.to_string()]).await?;
+        let Some(entries) = configs.remove(topic) else {
+            bail!("failed to describe_configs for topic {}", topic);
+        };
+
+        Ok(Some(resource::KafkaTopic {
+            config: topic_config_from_entries(entries),
+            ..topic_from_metadata(topic_metadata)
+        }))
+    }
+}
+
+/// Build a topic's config map from DescribeConfigs entries, skipping read-only and
+/// sensitive entries and rendering durations and sizes in human form.
+pub fn topic_config_from_entries(mut entries: Vec<ConfigEntry>) -> IndexMap<String, resource::KafkaConfigValue> {
+    let mut config_map = IndexMap::new();
+
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+    for entry in entries {
+        if entry.is_read_only {
+            continue;
+        }
+        if entry.is_sensitive {
+            continue;
+        }
+        if let Some(ref value) = entry.value {
+            config_map.insert(
+                entry.name.to_owned(),
+                resource::KafkaConfigValue::from_wire(&entry.name, value),
+            );
+        }
+    }
+
+    config_map
 }
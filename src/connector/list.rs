@@ -1,48 +1,47 @@
 use autoschematic_core::connector::ResourceAddress;
 use autoschematic_core::glob::addr_matches_filter;
+use futures_util::future::try_join_all;
 use std::path::{Path, PathBuf};
 
-use crate::{KafkaConnector, addr::KafkaResourceAddress, client::get_operation_timeout};
+use crate::{KafkaConnector, addr::KafkaResourceAddress};
 
 impl KafkaConnector {
     pub async fn do_list(&self, subpath: &Path) -> anyhow::Result<Vec<PathBuf>> {
+        let mut cluster_names: Vec<String> = self.config.read().await.clusters.keys().cloned().collect();
+        cluster_names.sort();
+
+        cluster_names.retain(|cluster_name| addr_matches_filter(&PathBuf::from(format!("kafka/{}", cluster_name)), subpath));
+
+        // Clusters are listed concurrently; fetch_metadata bounds how many requests are in flight.
+        let results = try_join_all(cluster_names.iter().map(|cluster_name| self.list_cluster(cluster_name))).await?;
+
+        Ok(results.into_iter().flatten().collect())
+    }
+
+    async fn list_cluster(&self, cluster_name: &str) -> anyhow::Result<Vec<PathBuf>> {
         let mut results = Vec::new();
-        let clients = self.clients.read().await;
-        let config = self.config.read().await;
-        let timeout = get_operation_timeout(config.operation_timeout_ms);
 
-        for (cluster_name, client) in clients.iter() {
-            let cluster_path = PathBuf::from(format!("kafka/{}", cluster_name));
+        // A cluster we can't reach is an error rather than an empty list, which
+        // would otherwise read as every topic in it having been deleted.
+        let metadata = self.fetch_metadata(cluster_name, None).await?;
 
-            if !addr_matches_filter(&cluster_path, subpath) {
+        // List topics
+        for topic in metadata.topics() {
+            let topic_name = topic.name();
+            // Skip internal Kafka topics
+            if topic_name.starts_with("__") {
                 continue;
             }
 
-            // List topics
-            match client.inner().fetch_metadata(None, timeout) {
-                Ok(metadata) => {
-                    for topic in metadata.topics() {
-                        let topic_name = topic.name();
-                        // Skip internal Kafka topics
-                        if topic_name.starts_with("__") {
-                            continue;
-                        }
-
-                        let addr = KafkaResourceAddress::Topic {
-                            cluster: cluster_name.clone(),
-                            topic: topic_name.to_string(),
-                        };
-                        results.push(addr.to_path_buf());
-                    }
-                }
-                Err(e) => {
-                    tracing::warn!("Failed to fetch metadata for cluster '{}': {}", cluster_name, e);
-                }
-            }
-            
-            // TODO list ACLs and Quotas when we have support
+            let addr = KafkaResourceAddress::Topic {
+                cluster: cluster_name.to_string(),
+                topic: topic_name.to_string(),
+            };
+            results.push(addr.to_path_buf());
         }
 
+        // TODO list ACLs and Quotas when we have support
+
         Ok(results)
     }
 }
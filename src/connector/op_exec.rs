@@ -1,5 +1,8 @@
-use crate::{KafkaConnector, addr::KafkaResourceAddress, client::get_operation_timeout, op::KafkaConnectorOp};
-use anyhow::{anyhow, bail};
+use crate::{
+    KafkaConnector, acl_state, addr::KafkaResourceAddress, client::get_operation_timeout, config::KafkaConnectorConfig,
+    lifecycle, op::KafkaConnectorOp, resource::KafkaTopicLifecycle, shared_state::KafkaSharedTopicState,
+};
+use anyhow::{Context, bail};
 use autoschematic_core::{
     connector::{ConnectorOp, OpExecResponse, ResourceAddress},
     error_util::invalid_op,
@@ -13,155 +16,306 @@ impl KafkaConnector {
         let addr = KafkaResourceAddress::from_path(addr)?;
         let op = KafkaConnectorOp::from_str(op)?;
 
-        match &addr {
-            KafkaResourceAddress::Config => Err(invalid_op(&addr, &op)),
-            KafkaResourceAddress::Task { .. } => Err(invalid_op(&addr, &op)),
-            KafkaResourceAddress::Topic { cluster, topic } => {
-                let clients = self.clients.read().await;
-                let config = self.config.read().await;
-                let timeout = get_operation_timeout(config.operation_timeout_ms);
+        // Enforce read-only and protected clusters before anything is dispatched
+        if let Some(cluster) = addr.cluster() {
+            self.check_cluster_mode(cluster, op.is_destructive()).await?;
+        }
 
-                let opts = AdminOptions::new().operation_timeout(Some(timeout));
+        let res = self.exec_op(&addr, op).await;
 
-                let client = clients
-                    .get(cluster)
-                    .ok_or_else(|| anyhow!("Cluster '{}' not found", cluster))?;
+        // Whatever the outcome, cached topic state for this cluster may now be stale
+        if let Some(cluster) = addr.cluster() {
+            self.invalidate_cache(cluster).await;
+        }
+
+        res
+    }
 
-                match op {
-                    KafkaConnectorOp::CreateTopic(topic_config) => {
-                        let new_topic = NewTopic::new(
+    async fn exec_op(&self, addr: &KafkaResourceAddress, op: KafkaConnectorOp) -> anyhow::Result<OpExecResponse> {
+        match addr {
+            KafkaResourceAddress::Config => match op {
+                KafkaConnectorOp::ReloadConfig => {
+                    let config = KafkaConnectorConfig::try_load(&self.prefix)?.unwrap_or_default();
+                    let diff = self.reload_config(config).await;
+                    Ok(OpExecResponse {
+                        outputs: None,
+                        friendly_message: Some(format!("Reloaded kafka/config.ron: {}", diff.describe())),
+                    })
+                }
+                _ => Err(invalid_op(addr, &op)),
+            },
+            KafkaResourceAddress::Task { .. } | KafkaResourceAddress::Profile { .. } => Err(invalid_op(addr, &op)),
+            KafkaResourceAddress::SharedTopic { topic } => match op {
+                KafkaConnectorOp::ApplyToCluster { cluster, op } => {
+                    // Run through do_op_exec so the cluster's mode, deletion checks and cache apply
+                    let cluster_addr = KafkaResourceAddress::Topic {
+                        cluster: cluster.clone(),
+                        topic: topic.clone(),
+                    };
+                    let applied = !matches!(*op, KafkaConnectorOp::DeleteTopic { .. });
+                    if !applied && lifecycle::recorded(&self.prefix, addr, Some(cluster))?.prevent_destroy {
+                        bail!(
+                            "Topic '{}' in cluster '{}' has lifecycle.prevent_destroy set; refusing to delete it",
                             topic,
-                            topic_config.partitions,
-                            rdkafka::admin::TopicReplication::Fixed(topic_config.replication_factor as i32),
+                            cluster
                         );
+                    }
 
-                        // Apply topic configurations
-                        let new_topic = topic_config
-                            .config
-                            .iter()
-                            .fold(new_topic, |nt, (key, value)| nt.set(key, value));
+                    let mut res = Box::pin(self.do_op_exec(&cluster_addr.to_path_buf(), &op.to_string()?)).await?;
 
-                        match client.create_topics(&[new_topic], &opts).await {
-                            Ok(results) => {
-                                if results.is_empty() {
-                                    bail!("No result returned from create_topics");
-                                }
+                    // The cluster's lifecycle markers are recorded on the shared definition
+                    res.outputs = res.outputs.map(|outputs| lifecycle::scoped_outputs(cluster, outputs));
 
-                                match &results[0] {
-                                    Ok(_topic_name) => Ok(OpExecResponse {
-                                        outputs: None,
-                                        friendly_message: Some(format!("Created topic '{}' in cluster '{}'", topic, cluster)),
-                                    }),
-                                    Err((topic_name, err)) => {
-                                        bail!("Failed to create topic '{}': {:?}", topic_name, err)
+                    // Record which clusters the definition covers, so that dropping one from it
+                    // later deletes the topic there and nowhere else
+                    if !self.config.read().await.validate_only {
+                        KafkaSharedTopicState::set_applied(&self.prefix, topic, cluster, applied)?;
+                    }
+
+                    Ok(res)
+                }
+                _ => Err(invalid_op(addr, &op)),
+            },
+            KafkaResourceAddress::Topic { cluster, topic } => {
+                let client = self.client(cluster).await?;
+                let (timeout, validate_only, archive_dir) = {
+                    let config = self.config.read().await;
+                    (
+                        get_operation_timeout(config.operation_timeout_ms),
+                        config.validate_only,
+                        config.archive_dir.clone(),
+                    )
+                };
+
+                let opts = AdminOptions::new()
+                    .operation_timeout(Some(timeout))
+                    .validate_only(validate_only);
+
+                // Lifecycle settings live in the topic's outputs, not in the cluster
+                if let KafkaConnectorOp::UpdateTopicLifecycle(lifecycle) = &op {
+                    return Ok(OpExecResponse {
+                        outputs: (!validate_only).then(|| lifecycle::outputs(lifecycle)),
+                        friendly_message: Some(format!(
+                            "{}Set prevent_destroy to {} and force_delete to {} for topic '{}' in cluster '{}'",
+                            if validate_only { "[validate-only] Would have: " } else { "" },
+                            lifecycle.prevent_destroy,
+                            lifecycle.force_delete,
+                            topic,
+                            cluster
+                        )),
+                    });
+                }
+
+                let lifecycle_after = match &op {
+                    KafkaConnectorOp::CreateTopic(topic_config) => Some(topic_config.lifecycle.clone()),
+                    KafkaConnectorOp::DeleteTopic { .. } => Some(KafkaTopicLifecycle::default()),
+                    _ => None,
+                };
+
+                // Check the marker again, in case it changed since the plan
+                if matches!(op, KafkaConnectorOp::DeleteTopic { .. })
+                    && lifecycle::recorded(&self.prefix, addr, None)?.prevent_destroy
+                {
+                    bail!(
+                        "Topic '{}' in cluster '{}' has lifecycle.prevent_destroy set; refusing to delete it",
+                        topic,
+                        cluster
+                    );
+                }
+
+                if let KafkaConnectorOp::DeleteTopic { force: false } = op {
+                    self.check_topic_unused(cluster, topic).await?;
+                }
+
+                if validate_only && matches!(op, KafkaConnectorOp::DeleteTopic { .. }) {
+                    // DeleteTopics has no validate-only mode on the broker, so never send it.
+                    return Ok(OpExecResponse {
+                        outputs: None,
+                        friendly_message: Some(format!(
+                            "[validate-only] Skipped deleting topic '{}' in cluster '{}' (brokers cannot validate deletes)",
+                            topic, cluster
+                        )),
+                    });
+                }
+
+                // Archive failures abandon the delete, so no data is lost without a copy
+                let archived = match (&op, &archive_dir) {
+                    (KafkaConnectorOp::DeleteTopic { .. }, Some(archive_dir)) => {
+                        let (path, _) = self.archive_topic(cluster, topic, archive_dir).await?;
+                        Some(path)
+                    }
+                    _ => None,
+                };
+
+                // Broker errors are collected here rather than returned directly, so that in
+                // validate-only mode they can be reported as the broker's verdict.
+                let res: anyhow::Result<OpExecResponse> = async {
+                    match op {
+                        KafkaConnectorOp::CreateTopic(topic_config) => {
+                            let new_topic = NewTopic::new(
+                                topic,
+                                topic_config.partitions,
+                                rdkafka::admin::TopicReplication::Fixed(topic_config.replication_factor as i32),
+                            );
+
+                            // Apply topic configurations
+                            let wire_config = topic_config.wire_config()?;
+                            let new_topic = wire_config
+                                .iter()
+                                .fold(new_topic, |nt, (key, value)| nt.set(key, value));
+
+                            match client.create_topics(&[new_topic], &opts).await {
+                                Ok(results) => {
+                                    if results.is_empty() {
+                                        bail!("No result returned from create_topics");
+                                    }
+
+                                    match &results[0] {
+                                        Ok(_topic_name) => Ok(OpExecResponse {
+                                            outputs: None,
+                                            friendly_message: Some(format!("Created topic '{}' in cluster '{}'", topic, cluster)),
+                                        }),
+                                        Err((topic_name, err)) => {
+                                            bail!("Failed to create topic '{}': {:?}", topic_name, err)
+                                        }
                                     }
                                 }
+                                Err(e) => bail!("Failed to create topic '{}': {:?}", topic, e),
                             }
-                            Err(e) => bail!("Failed to create topic '{}': {:?}", topic, e),
                         }
-                    }
-                    KafkaConnectorOp::UpdateTopicPartitions { partitions } => {
-                        // Use create_partitions to increase partition count
-                        use rdkafka::admin::NewPartitions;
+                        KafkaConnectorOp::UpdateTopicPartitions { partitions } => {
+                            // Use create_partitions to increase partition count
+                            use rdkafka::admin::NewPartitions;
 
-                        let new_partitions = NewPartitions::new(topic, partitions as usize);
+                            let new_partitions = NewPartitions::new(topic, partitions as usize);
 
-                        match client.create_partitions(&[new_partitions], &opts).await {
-                            Ok(results) => {
-                                if results.is_empty() {
-                                    bail!("No result returned from create_partitions");
-                                }
+                            match client.create_partitions(&[new_partitions], &opts).await {
+                                Ok(results) => {
+                                    if results.is_empty() {
+                                        bail!("No result returned from create_partitions");
+                                    }
 
-                                match &results[0] {
-                                    Ok(_topic_name) => Ok(OpExecResponse {
-                                        outputs: None,
-                                        friendly_message: Some(format!(
-                                            "Increased partitions for topic '{}' to {} in cluster '{}'",
-                                            topic, partitions, cluster
-                                        )),
-                                    }),
-                                    Err((topic_name, err)) => {
-                                        bail!("Failed to update partitions for topic '{}': {:?}", topic_name, err)
+                                    match &results[0] {
+                                        Ok(_topic_name) => Ok(OpExecResponse {
+                                            outputs: None,
+                                            friendly_message: Some(format!(
+                                                "Increased partitions for topic '{}' to {} in cluster '{}'",
+                                                topic, partitions, cluster
+                                            )),
+                                        }),
+                                        Err((topic_name, err)) => {
+                                            bail!("Failed to update partitions for topic '{}': {:?}", topic_name, err)
+                                        }
                                     }
                                 }
+                                Err(e) => bail!("Failed to update partitions for topic '{}': {:?}", topic, e),
                             }
-                            Err(e) => bail!("Failed to update partitions for topic '{}': {:?}", topic, e),
                         }
-                    }
-                    KafkaConnectorOp::UpdateTopicConfig { config: topic_config } => {
-                        let topic_config = HashMap::from_iter(topic_config.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+                        KafkaConnectorOp::UpdateTopicConfig { config: topic_config } => {
+                            let topic_config = HashMap::from_iter(topic_config.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+
+                            let alter_config = AlterConfig {
+                                specifier: rdkafka::admin::ResourceSpecifier::Topic(&topic),
+                                entries: topic_config,
+                            };
+
+                            match client.alter_configs(&[alter_config], &opts).await {
+                                Ok(results) => {
+                                    if results.is_empty() {
+                                        bail!("No result returned from alter_configs");
+                                    }
 
-                        let alter_config = AlterConfig {
-                            specifier: rdkafka::admin::ResourceSpecifier::Topic(&topic),
-                            entries: topic_config,
-                        };
+                                    match &results[0] {
+                                        Ok(_topic_name) => Ok(OpExecResponse {
+                                            outputs: None,
+                                            friendly_message: Some(format!(
+                                                "Altered config for topic '{}' in cluster '{}'",
+                                                topic, cluster
+                                            )),
+                                        }),
+                                        Err((_topic_name, err)) => {
+                                            bail!("Failed to update partitions for topic '{}': {:?}", topic, err)
+                                        }
+                                    }
+                                }
+                                Err(e) => {
+                                    bail!("Failed to alter config for topic '{}': {:?}", topic, e)
+                                }
+                            }
 
-                        match client.alter_configs(&[alter_config], &opts).await {
+                            // Ok(OpExecResponse {
+                            //     outputs: None,
+                            //     friendly_message: Some(format!(
+                            //         "Topic config update requested for '{}' (not yet implemented)",
+                            //         topic
+                            //     )),
+                            // })
+                        }
+                        KafkaConnectorOp::DeleteTopic { .. } => match client.delete_topics(&[topic], &opts).await {
                             Ok(results) => {
                                 if results.is_empty() {
-                                    bail!("No result returned from alter_configs");
+                                    bail!("No result returned from delete_topics");
                                 }
 
                                 match &results[0] {
                                     Ok(_topic_name) => Ok(OpExecResponse {
                                         outputs: None,
-                                        friendly_message: Some(format!(
-                                            "Altered config for topic '{}' in cluster '{}'",
-                                            topic, cluster
-                                        )),
+                                        friendly_message: Some(match &archived {
+                                            Some(path) => format!(
+                                                "Deleted topic '{}' from cluster '{}' after archiving it to {}",
+                                                topic,
+                                                cluster,
+                                                path.display()
+                                            ),
+                                            None => format!("Deleted topic '{}' from cluster '{}'", topic, cluster),
+                                        }),
                                     }),
-                                    Err((_topic_name, err)) => {
-                                        bail!("Failed to update partitions for topic '{}': {:?}", topic, err)
+                                    Err((topic_name, err)) => {
+                                        bail!("Failed to delete topic '{}': {:?}", topic_name, err)
                                     }
                                 }
                             }
-                            Err(e) => {
-                                bail!("Failed to alter config for topic '{}': {:?}", topic, e)
-                            }
-                        }
+                            Err(e) => bail!("Failed to delete topic '{}': {:?}", topic, e),
+                        },
+                        _ => Err(invalid_op(addr, &op)),
+                    }
+                }
+                .await;
 
-                        // Ok(OpExecResponse {
-                        //     outputs: None,
-                        //     friendly_message: Some(format!(
-                        //         "Topic config update requested for '{}' (not yet implemented)",
-                        //         topic
-                        //     )),
-                        // })
+                // A created topic records the markers it starts with; a deleted one clears them
+                let res = res.map(|mut res| {
+                    if !validate_only && let Some(lifecycle) = &lifecycle_after {
+                        res.outputs = Some(lifecycle::outputs(lifecycle));
                     }
-                    KafkaConnectorOp::DeleteTopic => match client.delete_topics(&[topic], &opts).await {
-                        Ok(results) => {
-                            if results.is_empty() {
-                                bail!("No result returned from delete_topics");
-                            }
+                    res
+                });
 
-                            match &results[0] {
-                                Ok(_topic_name) => Ok(OpExecResponse {
-                                    outputs: None,
-                                    friendly_message: Some(format!("Deleted topic '{}' from cluster '{}'", topic, cluster)),
-                                }),
-                                Err((topic_name, err)) => {
-                                    bail!("Failed to delete topic '{}': {:?}", topic_name, err)
-                                }
-                            }
-                        }
-                        Err(e) => bail!("Failed to delete topic '{}': {:?}", topic, e),
-                    },
-                    _ => Err(invalid_op(&addr, &op)),
+                if validate_only {
+                    validate_only_verdict(topic, cluster, res)
+                } else {
+                    res
                 }
             }
-            KafkaResourceAddress::Acl { cluster, acl_id } => {
-                // TODO: Implement ACL operations when rdkafka supports them
-                // For production use, this would require using the Kafka Admin API directly
-                tracing::warn!(
-                    "ACL operations not yet implemented for cluster '{}', ACL '{}'",
-                    cluster,
-                    acl_id
-                );
-                Ok(OpExecResponse {
-                    outputs: None,
-                    friendly_message: Some("ACL operation not yet implemented".to_string()),
-                })
+            KafkaResourceAddress::Acl { cluster, .. } => {
+                let binding = match &op {
+                    KafkaConnectorOp::CreateAcl(acl) => Some((acl.clone(), true)),
+                    KafkaConnectorOp::DeleteAcl(acl) => Some((acl.clone(), false)),
+                    _ => None,
+                };
+
+                let mut res = self.exec_acl_op(addr, cluster, op).await?;
+
+                // Record which bindings belong to the file, so they can be found again once
+                // they are removed from it
+                if !self.config.read().await.validate_only
+                    && let Some((acl, created)) = binding
+                {
+                    res.outputs = Some(acl_state::outputs(&self.prefix, addr, &acl, created)?);
+                }
+
+                Ok(res)
             }
+            KafkaResourceAddress::Access { cluster, .. } => self.exec_acl_op(addr, cluster, op).await,
             KafkaResourceAddress::Quota { cluster, quota_id } => {
                 // TODO: Implement quota operations when rdkafka supports them
                 // For production use, this would require using the Kafka Admin API directly
@@ -177,4 +331,69 @@ impl KafkaConnector {
             }
         }
     }
+
+    /// Create or delete a single ACL binding
+    async fn exec_acl_op(
+        &self,
+        addr: &KafkaResourceAddress,
+        cluster: &str,
+        op: KafkaConnectorOp,
+    ) -> anyhow::Result<OpExecResponse> {
+        let validate_only = self.config.read().await.validate_only;
+
+        let friendly_message = match op {
+            // ACL requests have no validate-only mode on the broker, so never send them
+            KafkaConnectorOp::CreateAcl(acl) if validate_only => {
+                format!(
+                    "[validate-only] Skipped creating ACL in cluster '{}': {}",
+                    cluster,
+                    acl.describe()
+                )
+            }
+            KafkaConnectorOp::DeleteAcl(acl) if validate_only => {
+                format!(
+                    "[validate-only] Skipped deleting ACL in cluster '{}': {}",
+                    cluster,
+                    acl.describe()
+                )
+            }
+            KafkaConnectorOp::CreateAcl(acl) => {
+                let description = acl.describe();
+                self.create_acls(cluster, vec![acl]).await?;
+                format!("Created ACL in cluster '{}': {}", cluster, description)
+            }
+            KafkaConnectorOp::DeleteAcl(acl) => {
+                let description = acl.describe();
+                if self.delete_acls(cluster, vec![acl]).await? == 0 {
+                    tracing::warn!("ACL was already absent in cluster '{}': {}", cluster, description);
+                }
+                format!("Deleted ACL in cluster '{}': {}", cluster, description)
+            }
+            _ => return Err(invalid_op(addr, &op)),
+        };
+
+        Ok(OpExecResponse {
+            outputs: None,
+            friendly_message: Some(friendly_message),
+        })
+    }
+}
+
+/// Report the broker's verdict on a validate-only request. Rejections fail the op, naming
+/// the change as validate-only, so a dry run fails when the brokers refuse the change.
+fn validate_only_verdict(topic: &str, cluster: &str, res: anyhow::Result<OpExecResponse>) -> anyhow::Result<OpExecResponse> {
+    res.with_context(|| {
+        format!(
+            "[validate-only] Brokers rejected change to topic '{}' in cluster '{}'",
+            topic, cluster
+        )
+    })?;
+
+    Ok(OpExecResponse {
+        outputs: None,
+        friendly_message: Some(format!(
+            "[validate-only] Brokers accepted change to topic '{}' in cluster '{}'",
+            topic, cluster
+        )),
+    })
 }
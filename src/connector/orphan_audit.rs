@@ -0,0 +1,324 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use autoschematic_core::{connector::ResourceAddress, util::RON};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    KafkaConnector,
+    acl::KafkaAclFilter,
+    addr::KafkaResourceAddress,
+    client::get_operation_timeout,
+    resource::{
+        KafkaAccess, KafkaAcl, KafkaAclSet, KafkaPrincipal, KafkaQuota, KafkaQuotaEntity, KafkaQuotaEntityType,
+        KafkaResourcePatternType, KafkaResourceType,
+    },
+    task::KafkaOrphanAuditTask,
+};
+
+/// A binding naming a topic or group that doesn't exist
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct KafkaOrphanAcl {
+    pub binding: KafkaAcl,
+    /// Whether the binding exists in the cluster, rather than only in a file
+    pub live: bool,
+    /// Files that declare the binding, relative to the repository root
+    pub files: Vec<PathBuf>,
+}
+
+/// A quota whose client IDs or IP addresses no connected consumer uses. Producers and
+/// consumers outside groups don't appear in group membership, so this is unverified: the
+/// clients may still be connected.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct KafkaOrphanQuota {
+    pub file: PathBuf,
+    pub inactive: Vec<KafkaQuotaEntity>,
+}
+
+/// A change to the repository that would remove orphaned bindings. Quotas are never suggested,
+/// since their findings are unverified.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum KafkaFileSuggestion {
+    /// Everything in the file is orphaned
+    Delete(PathBuf),
+    /// Some of the file's bindings are orphaned; for access files, remove the entries that expand to them
+    RemoveBindings { file: PathBuf, bindings: Vec<KafkaAcl> },
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct KafkaOrphanReport {
+    pub cluster: String,
+    pub acls: Vec<KafkaOrphanAcl>,
+    pub quotas: Vec<KafkaOrphanQuota>,
+    /// Whether any consumer group had members to check quotas against
+    pub client_activity_seen: bool,
+    pub suggestions: Vec<KafkaFileSuggestion>,
+}
+
+impl KafkaOrphanReport {
+    pub fn render(&self) -> String {
+        let mut lines = vec![format!("Orphan audit of cluster '{}'", self.cluster)];
+
+        if self.acls.is_empty() {
+            lines.push(String::from("No ACL bindings name missing topics or groups"));
+        }
+        for orphan in &self.acls {
+            let declared = if orphan.files.is_empty() {
+                String::from("not declared in any file")
+            } else {
+                let files: Vec<String> = orphan.files.iter().map(|f| f.display().to_string()).collect();
+                format!("declared in {}", files.join(", "))
+            };
+            lines.push(format!(
+                "  {}: {}; {}",
+                if orphan.live { "live" } else { "not applied" },
+                orphan.binding.describe(),
+                declared
+            ));
+        }
+
+        if !self.client_activity_seen {
+            lines.push(String::from(
+                "No consumer group has members, so quotas were not checked against client activity",
+            ));
+        } else if self.quotas.is_empty() {
+            lines.push(String::from(
+                "Every quota's client IDs and IP addresses are used by consumer group members",
+            ));
+        } else {
+            lines.push(String::from(
+                "Quotas no consumer group member uses (unverified: producers don't appear in groups, so check before deleting):",
+            ));
+        }
+        for orphan in &self.quotas {
+            let entities: Vec<String> = orphan.inactive.iter().map(KafkaQuotaEntity::describe).collect();
+            lines.push(format!("  {}: {}", orphan.file.display(), entities.join(", ")));
+        }
+
+        if !self.suggestions.is_empty() {
+            lines.push(String::from("Suggested changes:"));
+        }
+        for suggestion in &self.suggestions {
+            match suggestion {
+                KafkaFileSuggestion::Delete(file) => lines.push(format!("  delete {}", file.display())),
+                KafkaFileSuggestion::RemoveBindings { file, bindings } => {
+                    lines.push(format!("  remove {} binding(s) from {}", bindings.len(), file.display()))
+                }
+            }
+        }
+
+        lines.join("\n")
+    }
+}
+
+/// Consumer groups in a cluster and the client IDs and hosts of their members
+#[derive(Debug, Default)]
+struct KafkaClientActivity {
+    groups: HashSet<String>,
+    client_ids: HashSet<String>,
+    hosts: HashSet<String>,
+}
+
+impl KafkaClientActivity {
+    fn has_members(&self) -> bool {
+        !self.client_ids.is_empty()
+    }
+
+    /// Named client ID and IP entities that no member matches. Users aren't visible in
+    /// group membership and default entities match everyone, so neither is reported.
+    fn inactive(&self, quota: &KafkaQuota) -> Vec<KafkaQuotaEntity> {
+        quota
+            .entities
+            .iter()
+            .filter(|entity| match (&entity.entity_type, &entity.name) {
+                (KafkaQuotaEntityType::ClientId, Some(name)) => !self.client_ids.contains(name),
+                (KafkaQuotaEntityType::Ip, Some(name)) => !self.hosts.contains(name),
+                _ => false,
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+/// Whether a binding names a single topic or group that doesn't exist
+fn is_orphaned(acl: &KafkaAcl, topics: &HashSet<String>, groups: &HashSet<String>) -> bool {
+    if acl.pattern_type != KafkaResourcePatternType::Literal || acl.resource_name == "*" {
+        return false;
+    }
+
+    match acl.resource_type {
+        KafkaResourceType::Topic => !topics.contains(&acl.resource_name),
+        KafkaResourceType::Group => !groups.contains(&acl.resource_name),
+        _ => false,
+    }
+}
+
+/// The `.ron` files in a directory by file stem, or none if it doesn't exist
+fn ron_files(dir: &Path) -> anyhow::Result<Vec<(String, PathBuf)>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "ron")
+            && let Some(stem) = path.file_stem().and_then(|s| s.to_str())
+        {
+            files.push((stem.to_string(), path.clone()));
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+impl KafkaConnector {
+    async fn client_activity(&self, cluster: &str) -> anyhow::Result<KafkaClientActivity> {
+        let client = self.client(cluster).await?;
+        let timeout = get_operation_timeout(self.config.read().await.operation_timeout_ms);
+        let _permit = self.permit().await?;
+
+        tokio::task::spawn_blocking(move || {
+            let mut activity = KafkaClientActivity::default();
+            for group in client.inner().fetch_group_list(None, timeout)?.groups() {
+                activity.groups.insert(group.name().to_string());
+                for member in group.members() {
+                    activity.client_ids.insert(member.client_id().to_string());
+                    // Hosts are reported as `/10.0.0.1`
+                    activity
+                        .hosts
+                        .insert(member.client_host().trim_start_matches('/').to_string());
+                }
+            }
+            Ok::<_, anyhow::Error>(activity)
+        })
+        .await?
+        .with_context(|| format!("Failed to list consumer groups in cluster '{}'", cluster))
+    }
+
+    /// Bindings declared by each ACL and access file in a cluster, keyed by the file's
+    /// path relative to the repository root
+    fn declared_bindings(&self, cluster: &str) -> anyhow::Result<BTreeMap<PathBuf, BTreeSet<KafkaAcl>>> {
+        let mut declared = BTreeMap::new();
+
+        for (acl_id, path) in ron_files(&self.prefix.join("kafka").join(cluster).join("acls"))? {
+            let set = KafkaAclSet::parse(&std::fs::read_to_string(&path)?)
+                .with_context(|| format!("Failed to parse {}", path.display()))?;
+            let addr = KafkaResourceAddress::Acl {
+                cluster: cluster.to_string(),
+                acl_id,
+            };
+            declared.insert(addr.to_path_buf(), set.binding_set());
+        }
+
+        for (principal, path) in ron_files(&self.prefix.join("kafka").join(cluster).join("access"))? {
+            let access: KafkaAccess = RON
+                .from_str(&std::fs::read_to_string(&path)?)
+                .with_context(|| format!("Failed to parse {}", path.display()))?;
+            let bindings = access.bindings(&KafkaPrincipal::from_file_stem(&principal)?);
+            let addr = KafkaResourceAddress::Access {
+                cluster: cluster.to_string(),
+                principal,
+            };
+            declared.insert(addr.to_path_buf(), bindings);
+        }
+
+        Ok(declared)
+    }
+
+    /// Cross-reference the cluster's bindings and quota files against its topics, groups
+    /// and consumer group members
+    pub async fn audit_orphans(&self, task: &KafkaOrphanAuditTask) -> anyhow::Result<KafkaOrphanReport> {
+        let cluster = &task.cluster;
+
+        let metadata = self.fetch_metadata(cluster, None).await?;
+        let topics: HashSet<String> = metadata.topics().iter().map(|t| t.name().to_string()).collect();
+        let activity = self.client_activity(cluster).await?;
+
+        let live: BTreeSet<KafkaAcl> = self
+            .describe_acls(cluster, KafkaAclFilter::default())
+            .await?
+            .into_iter()
+            .collect();
+        let declared = self.declared_bindings(cluster)?;
+
+        let mut report = KafkaOrphanReport {
+            cluster: cluster.clone(),
+            client_activity_seen: activity.has_members(),
+            ..Default::default()
+        };
+
+        let candidates: BTreeSet<&KafkaAcl> = live
+            .iter()
+            .chain(declared.values().flatten())
+            .filter(|acl| is_orphaned(acl, &topics, &activity.groups))
+            .collect();
+
+        for acl in candidates {
+            report.acls.push(KafkaOrphanAcl {
+                binding: acl.clone(),
+                live: live.contains(acl),
+                files: declared
+                    .iter()
+                    .filter(|(_, bindings)| bindings.contains(acl))
+                    .map(|(file, _)| file.clone())
+                    .collect(),
+            });
+        }
+
+        for (file, bindings) in &declared {
+            let orphaned: Vec<KafkaAcl> = bindings
+                .iter()
+                .filter(|acl| is_orphaned(acl, &topics, &activity.groups))
+                .cloned()
+                .collect();
+
+            if orphaned.is_empty() {
+                continue;
+            }
+            if orphaned.len() == bindings.len() {
+                report.suggestions.push(KafkaFileSuggestion::Delete(file.clone()));
+            } else {
+                report.suggestions.push(KafkaFileSuggestion::RemoveBindings {
+                    file: file.clone(),
+                    bindings: orphaned,
+                });
+            }
+        }
+
+        if activity.has_members() {
+            for (quota_id, path) in ron_files(&self.prefix.join("kafka").join(cluster).join("quotas"))? {
+                let quota: KafkaQuota = RON
+                    .from_str(&std::fs::read_to_string(&path)?)
+                    .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+                let inactive = activity.inactive(&quota);
+                if inactive.is_empty() {
+                    continue;
+                }
+
+                let file = KafkaResourceAddress::Quota {
+                    cluster: cluster.clone(),
+                    quota_id,
+                }
+                .to_path_buf();
+                report.quotas.push(KafkaOrphanQuota { file, inactive });
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Run the orphan audit task, writing the report as RON if the task asks for it
+    pub async fn audit_orphans_task(&self, task: &KafkaOrphanAuditTask) -> anyhow::Result<String> {
+        let report = self.audit_orphans(task).await?;
+
+        if let Some(report_path) = &task.report_path {
+            self.write_report(report_path, &report)?;
+        }
+
+        Ok(report.render())
+    }
+}
@@ -1,10 +1,19 @@
-use crate::{KafkaConnector, addr::KafkaResourceAddress, op::KafkaConnectorOp, resource};
-use anyhow::Context;
+use crate::{
+    KafkaConnector, acl_state,
+    addr::{KafkaResourceAddress, SHARED_DIR},
+    config::{KafkaClusterMode, KafkaConnectorConfig},
+    op::KafkaConnectorOp,
+    policy, profile, resource,
+};
+use anyhow::{Context, bail};
 use autoschematic_core::{
     connector::{ConnectorOp, PlanResponseElement, Resource, ResourceAddress},
     connector_op,
 };
-use std::path::Path;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::Path,
+};
 
 impl KafkaConnector {
     pub async fn do_plan(
@@ -14,14 +23,89 @@ impl KafkaConnector {
         desired: Option<Vec<u8>>,
     ) -> anyhow::Result<Vec<PlanResponseElement>> {
         let addr = KafkaResourceAddress::from_path(addr)?;
+        let cluster = addr.cluster().map(String::from);
+
+        let mut ops = match addr {
+            KafkaResourceAddress::Topic { .. } => self.plan_topic(addr, current, desired).await?,
+            KafkaResourceAddress::SharedTopic { .. } => self.plan_shared_topic(addr, current, desired).await?,
+            KafkaResourceAddress::Acl { .. } => self.plan_acl(addr, current, desired).await?,
+            KafkaResourceAddress::Access { .. } => self.plan_access(addr, current, desired).await?,
+            KafkaResourceAddress::Quota { .. } => self.plan_quota(addr, current, desired).await?,
+            KafkaResourceAddress::Config => self.plan_config(current, desired).await?,
+            // Profiles take effect when the topics that use them are planned
+            KafkaResourceAddress::Profile { .. } | KafkaResourceAddress::Task { .. } => vec![],
+        };
 
-        match addr {
-            KafkaResourceAddress::Topic { .. } => self.plan_topic(addr, current, desired).await,
-            KafkaResourceAddress::Acl { .. } => self.plan_acl(addr, current, desired).await,
-            KafkaResourceAddress::Quota { .. } => self.plan_quota(addr, current, desired).await,
-            KafkaResourceAddress::Config => Ok(vec![]),
-            KafkaResourceAddress::Task { .. } => Ok(vec![]),
+        if let Some(cluster) = cluster {
+            self.annotate_cluster_mode(&cluster, &mut ops).await?;
+        }
+
+        Ok(ops)
+    }
+
+    /// Note in each op's description when the cluster's mode will reject it at apply time
+    async fn annotate_cluster_mode(&self, cluster: &str, ops: &mut [PlanResponseElement]) -> anyhow::Result<()> {
+        let mode = self.planned_cluster_config(cluster).await?.mode;
+
+        for op in ops.iter_mut() {
+            let rejected = match mode {
+                KafkaClusterMode::ReadWrite => false,
+                KafkaClusterMode::ReadOnly => true,
+                KafkaClusterMode::Protected => KafkaConnectorOp::from_str(&op.op_definition)?.is_destructive(),
+            };
+
+            if rejected {
+                op.friendly_message = op
+                    .friendly_message
+                    .take()
+                    .map(|m| format!("{} (will be rejected: cluster '{}' is {})", m, cluster, mode));
+            }
         }
+
+        Ok(())
+    }
+
+    async fn plan_config(
+        &self,
+        current: Option<Vec<u8>>,
+        desired: Option<Vec<u8>>,
+    ) -> anyhow::Result<Vec<PlanResponseElement>> {
+        let Some(desired_bytes) = desired else {
+            return Ok(vec![]);
+        };
+
+        let current_config = match current {
+            Some(current_bytes) => KafkaConnectorConfig::from_bytes(&KafkaResourceAddress::Config, &current_bytes)
+                .context("Failed to parse current config")?,
+            None => KafkaConnectorConfig {
+                clusters: HashMap::new(),
+                ..Default::default()
+            },
+        };
+
+        let desired_config = KafkaConnectorConfig::from_bytes(&KafkaResourceAddress::Config, &desired_bytes)
+            .context("Failed to parse desired config")?;
+
+        if desired_config.clusters.contains_key(SHARED_DIR) {
+            bail!(
+                "'{}' is reserved for shared definitions at kafka/{}/ and can't be used as a cluster name",
+                SHARED_DIR,
+                SHARED_DIR
+            );
+        }
+
+        // The live config is left alone until the ReloadConfig op runs; topics on clusters added
+        // in the same change are planned against the staged file meanwhile.
+        let diff = current_config.diff(&desired_config);
+
+        if diff.is_empty() {
+            return Ok(vec![]);
+        }
+
+        Ok(vec![connector_op!(
+            KafkaConnectorOp::ReloadConfig,
+            format!("Reload Kafka config: {}", diff.describe())
+        )])
     }
 
     async fn plan_topic(
@@ -30,15 +114,24 @@ impl KafkaConnector {
         current: Option<Vec<u8>>,
         desired: Option<Vec<u8>>,
     ) -> anyhow::Result<Vec<PlanResponseElement>> {
+        let KafkaResourceAddress::Topic { ref cluster, ref topic } = addr else {
+            bail!("Expected topic address, got {:?}", addr);
+        };
+
         let mut ops = Vec::new();
 
         match (current, desired) {
             (None, None) => {} // Nothing to do
             (None, Some(desired_bytes)) => {
                 // Create new topic
-                let desired_topic: resource::KafkaTopic = resource::KafkaResource::from_bytes(&addr, &desired_bytes)
+                let desired_spec: resource::KafkaTopicSpec = resource::KafkaResource::from_bytes(&addr, &desired_bytes)
                     .context("Failed to parse desired topic")?
                     .into();
+                let desired_topic = profile::resolve_topic(&self.prefix, cluster, &desired_spec)?;
+                let desired_topic = desired_topic.normalized()?;
+
+                policy::check_topic(&self.prefix, cluster, topic, &desired_topic)?;
+                self.validate_topic_capacity(cluster, topic, None, &desired_topic).await?;
 
                 ops.push(connector_op!(
                     KafkaConnectorOp::CreateTopic(desired_topic.clone()),
@@ -48,19 +141,50 @@ impl KafkaConnector {
                     )
                 ));
             }
-            (Some(_), None) => {
+            (Some(current_bytes), None) => {
+                let current_spec: resource::KafkaTopicSpec = resource::KafkaResource::from_bytes(&addr, &current_bytes)
+                    .context("Failed to parse current topic")?
+                    .into();
+                let current_topic = profile::resolve_topic(&self.prefix, cluster, &current_spec)?;
+
+                // The current state carries the markers recorded in the topic's outputs
+                if current_topic.lifecycle.prevent_destroy {
+                    bail!(
+                        "Topic '{}' in cluster '{}' has lifecycle.prevent_destroy set; \
+                         remove the flag in a separate change before deleting the topic",
+                        topic,
+                        cluster
+                    );
+                }
+
                 // Delete topic
-                ops.push(connector_op!(KafkaConnectorOp::DeleteTopic, "Delete topic".to_string()));
+                let force = current_topic.lifecycle.force_delete;
+                ops.push(connector_op!(
+                    KafkaConnectorOp::DeleteTopic { force },
+                    if force {
+                        "Delete topic (forced: skipping consumer group and recent write checks)".to_string()
+                    } else {
+                        "Delete topic".to_string()
+                    }
+                ));
             }
             (Some(current_bytes), Some(desired_bytes)) => {
                 // Update topic
-                let current_topic: resource::KafkaTopic = resource::KafkaResource::from_bytes(&addr, &current_bytes)
+                let current_spec: resource::KafkaTopicSpec = resource::KafkaResource::from_bytes(&addr, &current_bytes)
                     .context("Failed to parse current topic")?
                     .into();
+                let current_topic = profile::resolve_topic(&self.prefix, cluster, &current_spec)?;
+                let current_topic = current_topic.normalized()?;
 
-                let desired_topic: resource::KafkaTopic = resource::KafkaResource::from_bytes(&addr, &desired_bytes)
+                let desired_spec: resource::KafkaTopicSpec = resource::KafkaResource::from_bytes(&addr, &desired_bytes)
                     .context("Failed to parse desired topic")?
                     .into();
+                let desired_topic = profile::resolve_topic(&self.prefix, cluster, &desired_spec)?;
+                let desired_topic = desired_topic.normalized()?;
+
+                policy::check_topic(&self.prefix, cluster, topic, &desired_topic)?;
+                self.validate_topic_capacity(cluster, topic, Some(&current_topic), &desired_topic)
+                    .await?;
 
                 // Check if partitions changed (can only increase)
                 if desired_topic.partitions > current_topic.partitions {
@@ -94,74 +218,408 @@ impl KafkaConnector {
                 if desired_topic.config != current_topic.config {
                     ops.push(connector_op!(
                         KafkaConnectorOp::UpdateTopicConfig {
-                            config: desired_topic.config.clone()
+                            config: desired_topic.wire_config()?
                         },
                         "Update topic configuration".to_string()
                     ));
                 }
+
+                // Check lifecycle changes
+                if desired_topic.lifecycle != current_topic.lifecycle {
+                    ops.push(connector_op!(
+                        KafkaConnectorOp::UpdateTopicLifecycle(desired_topic.lifecycle.clone()),
+                        format!(
+                            "Set prevent_destroy to {} and force_delete to {} for topic",
+                            desired_topic.lifecycle.prevent_destroy, desired_topic.lifecycle.force_delete
+                        )
+                    ));
+                }
             }
         }
 
         Ok(ops)
     }
 
-    async fn plan_acl(
+    /// Fan a shared topic definition out into per-cluster topic plans. Clusters dropped from
+    /// the definition have the topic deleted, subject to the usual deletion checks, but only
+    /// if the definition was applied to them.
+    async fn plan_shared_topic(
         &self,
         addr: KafkaResourceAddress,
         current: Option<Vec<u8>>,
         desired: Option<Vec<u8>>,
     ) -> anyhow::Result<Vec<PlanResponseElement>> {
-        let mut ops = Vec::new();
+        let KafkaResourceAddress::SharedTopic { ref topic } = addr else {
+            bail!("Expected shared topic address, got {:?}", addr);
+        };
 
-        match (current, desired) {
-            (None, None) => {}
-            (None, Some(desired_bytes)) => {
-                let desired_acl: resource::KafkaAcl = resource::KafkaResource::from_bytes(&addr, &desired_bytes)
-                    .context("Failed to parse desired ACL")?
+        let current_topics = match current {
+            Some(current_bytes) => {
+                let current: resource::KafkaSharedTopic = resource::KafkaResource::from_bytes(&addr, &current_bytes)
+                    .context("Failed to parse current shared topic")?
                     .into();
-
-                ops.push(connector_op!(
-                    KafkaConnectorOp::CreateAcl(desired_acl.clone()),
-                    format!("Create ACL for {:?} on {}", desired_acl.principal, desired_acl.resource_name)
-                ));
+                current.resolve_all()
             }
-            (Some(current_bytes), None) => {
-                let current_acl: resource::KafkaAcl = resource::KafkaResource::from_bytes(&addr, &current_bytes)
-                    .context("Failed to parse current ACL")?
+            None => BTreeMap::new(),
+        };
+
+        let desired_topics = match desired {
+            Some(desired_bytes) => {
+                let desired: resource::KafkaSharedTopic = resource::KafkaResource::from_bytes(&addr, &desired_bytes)
+                    .context("Failed to parse desired shared topic")?
                     .into();
+                desired.resolve_all()
+            }
+            None => BTreeMap::new(),
+        };
+
+        let managed_clusters = self.shared_topic_clusters(topic).await?;
+        for cluster in desired_topics.keys() {
+            if !managed_clusters.contains(cluster) {
+                bail!(
+                    "Shared topic '{}' lists cluster '{}', which is either not configured or already defines \
+                     the topic in kafka/{}/topics/{}.ron; a topic can only be defined in one place",
+                    topic,
+                    cluster,
+                    cluster,
+                    topic
+                );
+            }
+        }
+
+        let clusters: BTreeSet<&String> = current_topics.keys().chain(desired_topics.keys()).collect();
 
+        let mut ops = Vec::new();
+        for cluster in clusters {
+            let cluster_addr = KafkaResourceAddress::Topic {
+                cluster: cluster.clone(),
+                topic: topic.clone(),
+            };
+            let current = current_topics
+                .get(cluster)
+                .map(|t| resource::KafkaResource::Topic(t.clone().into()).to_bytes())
+                .transpose()?;
+            let desired = desired_topics
+                .get(cluster)
+                .map(|t| resource::KafkaResource::Topic(t.clone().into()).to_bytes())
+                .transpose()?;
+
+            let mut cluster_ops = self
+                .plan_topic(cluster_addr, current, desired)
+                .await
+                .with_context(|| format!("Failed to plan topic '{}' in cluster '{}'", topic, cluster))?;
+            self.annotate_cluster_mode(cluster, &mut cluster_ops).await?;
+
+            for cluster_op in cluster_ops {
+                let op = KafkaConnectorOp::from_str(&cluster_op.op_definition)?;
                 ops.push(connector_op!(
-                    KafkaConnectorOp::DeleteAcl(current_acl),
-                    "Delete ACL".to_string()
+                    KafkaConnectorOp::ApplyToCluster {
+                        cluster: cluster.clone(),
+                        op: Box::new(op),
+                    },
+                    format!("[{}] {}", cluster, cluster_op.friendly_message.unwrap_or_default())
                 ));
             }
-            (Some(current_bytes), Some(desired_bytes)) => {
-                // ACLs are typically recreated rather than updated
-                let current_acl: resource::KafkaAcl = resource::KafkaResource::from_bytes(&addr, &current_bytes)
-                    .context("Failed to parse current ACL")?
-                    .into();
+        }
 
-                let desired_acl: resource::KafkaAcl = resource::KafkaResource::from_bytes(&addr, &desired_bytes)
-                    .context("Failed to parse desired ACL")?
-                    .into();
+        Ok(ops)
+    }
 
-                if current_acl != desired_acl {
-                    ops.push(connector_op!(
-                        KafkaConnectorOp::DeleteAcl(current_acl),
-                        "Delete old ACL".to_string()
-                    ));
-                    ops.push(connector_op!(
-                        KafkaConnectorOp::CreateAcl(desired_acl.clone()),
-                        format!(
-                            "Create new ACL for {:?} on {}",
-                            desired_acl.principal, desired_acl.resource_name
-                        )
-                    ));
+    /// Check a desired topic against live cluster metadata, so that changes the brokers
+    /// would reject fail at plan time rather than at apply time.
+    async fn validate_topic_capacity(
+        &self,
+        cluster: &str,
+        topic: &str,
+        current: Option<&resource::KafkaTopic>,
+        desired: &resource::KafkaTopic,
+    ) -> anyhow::Result<()> {
+        let max_partitions = self.planned_cluster_config(cluster).await?.max_partitions;
+
+        let metadata = self
+            .fetch_metadata(cluster, None)
+            .await
+            .with_context(|| format!("Failed to fetch metadata while planning topic '{}'", topic))?;
+
+        // Only new or raised replication factors need brokers to place replicas on; existing
+        // replicas stay put while a broker is down, so other changes go ahead.
+        let broker_count = metadata.brokers().len();
+        let raises_replication = current.is_none_or(|c| desired.replication_factor > c.replication_factor);
+        if raises_replication && desired.replication_factor as usize > broker_count {
+            bail!(
+                "Topic '{}' requests replication factor {} but cluster '{}' only has {} live broker(s); \
+                 lower replication_factor or add brokers before applying",
+                topic,
+                desired.replication_factor,
+                cluster,
+                broker_count
+            );
+        }
+
+        if let Some(max_partitions) = max_partitions {
+            let existing: i64 = metadata.topics().iter().map(|t| t.partitions().len() as i64).sum();
+            let added = (desired.partitions - current.map(|c| c.partitions).unwrap_or(0)).max(0) as i64;
+
+            if added > 0 && existing + added > max_partitions as i64 {
+                bail!(
+                    "Topic '{}' would add {} partition(s) to cluster '{}', which already has {} of its budget of {}; \
+                     reduce partitions or raise max_partitions in kafka/config.ron",
+                    topic,
+                    added,
+                    cluster,
+                    existing,
+                    max_partitions
+                );
+            }
+        }
+
+        // Brokers reject batches above their own message.max.bytes whatever the topic allows,
+        // so a topic limit above it is checked when it is set or raised
+        let max_message_bytes = desired.wire_config()?.get("max.message.bytes").cloned();
+        let current_max_message_bytes = match current {
+            Some(current) => current.wire_config()?.get("max.message.bytes").cloned(),
+            None => None,
+        };
+        if let Some(max_message_bytes) = max_message_bytes
+            && Some(&max_message_bytes) != current_max_message_bytes.as_ref()
+            && let Some(broker) = metadata.brokers().first()
+        {
+            let max_message_bytes: i64 = max_message_bytes
+                .parse()
+                .with_context(|| format!("Invalid max.message.bytes '{}' for topic '{}'", max_message_bytes, topic))?;
+
+            let broker_limit = self
+                .describe_broker_config(cluster, broker.id())
+                .await?
+                .into_iter()
+                .find(|entry| entry.name == "message.max.bytes")
+                .and_then(|entry| entry.value)
+                .and_then(|value| value.parse::<i64>().ok());
+
+            if let Some(broker_limit) = broker_limit
+                && max_message_bytes > broker_limit
+            {
+                bail!(
+                    "Topic '{}' sets max.message.bytes to {} but broker {} in cluster '{}' has message.max.bytes {}; \
+                     lower max.message.bytes or raise the broker limit",
+                    topic,
+                    max_message_bytes,
+                    broker.id(),
+                    cluster,
+                    broker_limit
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn plan_acl(
+        &self,
+        addr: KafkaResourceAddress,
+        current: Option<Vec<u8>>,
+        desired: Option<Vec<u8>>,
+    ) -> anyhow::Result<Vec<PlanResponseElement>> {
+        let KafkaResourceAddress::Acl { ref cluster, ref acl_id } = addr else {
+            bail!("Expected ACL address, got {:?}", addr);
+        };
+
+        let bindings = |bytes: Option<Vec<u8>>, what: &str| -> anyhow::Result<BTreeSet<resource::KafkaAcl>> {
+            match bytes {
+                Some(bytes) => {
+                    let set: resource::KafkaAclSet = resource::KafkaResource::from_bytes(&addr, &bytes)
+                        .with_context(|| format!("Failed to parse {} ACLs", what))?
+                        .into();
+                    Ok(set.binding_set())
                 }
+                None => Ok(BTreeSet::new()),
             }
+        };
+
+        let current_bindings = bindings(current, "current")?;
+        let desired_bindings = bindings(desired, "desired")?;
+
+        for acl in &desired_bindings {
+            acl.validate()?;
         }
 
-        Ok(ops)
+        let principals: BTreeSet<&resource::KafkaPrincipal> = desired_bindings.iter().map(|acl| &acl.principal).collect();
+        for principal in principals {
+            self.check_access_file_for_principal(cluster, principal)?;
+        }
+
+        self.check_acl_files_for_bindings(cluster, acl_id, &desired_bindings)?;
+
+        Ok(acl_diff_ops(&current_bindings, &desired_bindings))
+    }
+
+    async fn plan_access(
+        &self,
+        addr: KafkaResourceAddress,
+        current: Option<Vec<u8>>,
+        desired: Option<Vec<u8>>,
+    ) -> anyhow::Result<Vec<PlanResponseElement>> {
+        let KafkaResourceAddress::Access {
+            ref cluster,
+            ref principal,
+        } = addr
+        else {
+            bail!("Expected access address, got {:?}", addr);
+        };
+        let principal = resource::KafkaPrincipal::from_file_stem(principal)?;
+
+        let access = |bytes: Option<Vec<u8>>, what: &str| -> anyhow::Result<Option<resource::KafkaAccess>> {
+            match bytes {
+                Some(bytes) => Ok(Some(
+                    resource::KafkaResource::from_bytes(&addr, &bytes)
+                        .with_context(|| format!("Failed to parse {} access", what))?
+                        .into(),
+                )),
+                None => Ok(None),
+            }
+        };
+
+        let current_access = access(current, "current")?;
+        let desired_access = access(desired, "desired")?;
+
+        if let Some(desired_access) = &desired_access
+            && let Some(acl) = desired_access
+                .additional_acls
+                .iter()
+                .find(|acl| acl.host != desired_access.host)
+        {
+            bail!(
+                "Access file for {} is for host {}, but contains a binding for host {}: {}",
+                principal.to_wire(),
+                desired_access.host,
+                acl.host,
+                acl.describe()
+            );
+        }
+
+        let current_bindings = current_access.map(|access| access.bindings(&principal)).unwrap_or_default();
+        let desired_bindings = desired_access.map(|access| access.bindings(&principal)).unwrap_or_default();
+
+        for acl in &desired_bindings {
+            acl.validate()?;
+            if acl.principal != principal {
+                bail!(
+                    "Access file for {} contains a binding for {}: {}",
+                    principal.to_wire(),
+                    acl.principal.to_wire(),
+                    acl.describe()
+                );
+            }
+        }
+
+        if !desired_bindings.is_empty() {
+            self.check_acl_files_for_principal(cluster, &principal)?;
+        }
+
+        Ok(acl_diff_ops(&current_bindings, &desired_bindings))
+    }
+
+    /// An access file manages all of its principal's bindings, so it can't share the
+    /// principal with files under kafka/{cluster}/acls/
+    fn check_acl_files_for_principal(&self, cluster: &str, principal: &resource::KafkaPrincipal) -> anyhow::Result<()> {
+        let dir = self.prefix.join("kafka").join(cluster).join("acls");
+        if !dir.is_dir() {
+            return Ok(());
+        }
+
+        for entry in std::fs::read_dir(&dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+            let path = entry?.path();
+            if !path.extension().is_some_and(|ext| ext == "ron") {
+                continue;
+            }
+
+            let set = resource::KafkaAclSet::parse(&std::fs::read_to_string(&path)?)
+                .with_context(|| format!("Failed to parse {}", path.display()))?;
+            if set.bindings.iter().any(|acl| &acl.principal == principal) {
+                bail!(
+                    "{} has an access file in cluster '{}', which manages all of its ACLs, but {} also binds it",
+                    principal.to_wire(),
+                    cluster,
+                    path.display()
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Each binding belongs to one ACL file, since deleting it from either file would delete it
+    /// from the cluster. A binding still recorded for another file, which no longer lists it,
+    /// can only move once that file's plan has deleted it.
+    fn check_acl_files_for_bindings(
+        &self,
+        cluster: &str,
+        acl_id: &str,
+        desired: &BTreeSet<resource::KafkaAcl>,
+    ) -> anyhow::Result<()> {
+        if desired.is_empty() {
+            return Ok(());
+        }
+
+        let dir = self.prefix.join("kafka").join(cluster).join("acls");
+        if dir.is_dir() {
+            for entry in std::fs::read_dir(&dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+                let path = entry?.path();
+                if !path.extension().is_some_and(|ext| ext == "ron") || path.file_stem().is_some_and(|stem| stem == acl_id) {
+                    continue;
+                }
+
+                let set = resource::KafkaAclSet::parse(&std::fs::read_to_string(&path)?)
+                    .with_context(|| format!("Failed to parse {}", path.display()))?;
+                if let Some(acl) = set.bindings.iter().find(|acl| desired.contains(acl)) {
+                    bail!(
+                        "{} is also declared by {}; each binding can only be in one ACL file",
+                        acl.describe(),
+                        path.display()
+                    );
+                }
+            }
+        }
+
+        for other_id in acl_state::recorded_files(&self.prefix, cluster)? {
+            if other_id == acl_id {
+                continue;
+            }
+
+            let other_addr = KafkaResourceAddress::Acl {
+                cluster: cluster.to_string(),
+                acl_id: other_id.clone(),
+            };
+            let owned = acl_state::owned(&self.prefix, &other_addr)?;
+            if let Some(acl) = owned.iter().find(|acl| desired.contains(acl)) {
+                bail!(
+                    "{} was created by kafka/{}/acls/{}.ron; apply that file's plan to delete it before moving it here",
+                    acl.describe(),
+                    cluster,
+                    other_id
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The reverse of `check_acl_files_for_principal`, for plans of ACL files
+    fn check_access_file_for_principal(&self, cluster: &str, principal: &resource::KafkaPrincipal) -> anyhow::Result<()> {
+        let addr = KafkaResourceAddress::Access {
+            cluster: cluster.to_string(),
+            principal: principal.to_file_stem(),
+        };
+
+        if self.prefix.join(addr.to_path_buf()).exists() {
+            bail!(
+                "{} is managed by {}, which owns all of its ACLs; move these bindings there",
+                principal.to_wire(),
+                addr.to_path_buf().display()
+            );
+        }
+
+        Ok(())
     }
 
     async fn plan_quota(
@@ -178,10 +636,11 @@ impl KafkaConnector {
                 let desired_quota: resource::KafkaQuota = resource::KafkaResource::from_bytes(&addr, &desired_bytes)
                     .context("Failed to parse desired quota")?
                     .into();
+                desired_quota.validate()?;
 
                 ops.push(connector_op!(
                     KafkaConnectorOp::CreateQuota(desired_quota.clone()),
-                    "Create quota".to_string()
+                    format!("Create quota for {}", desired_quota.describe_entities())
                 ));
             }
             (Some(_), None) => {
@@ -195,11 +654,12 @@ impl KafkaConnector {
                 let desired_quota: resource::KafkaQuota = resource::KafkaResource::from_bytes(&addr, &desired_bytes)
                     .context("Failed to parse desired quota")?
                     .into();
+                desired_quota.validate()?;
 
                 if current_quota != desired_quota {
                     ops.push(connector_op!(
                         KafkaConnectorOp::UpdateQuota(desired_quota.clone()),
-                        "Update quota".to_string()
+                        format!("Update quota for {}", desired_quota.describe_entities())
                     ));
                 }
             }
@@ -209,8 +669,29 @@ impl KafkaConnector {
     }
 }
 
+/// Ops turning one set of ACL bindings into another. Grants go first, so access that is only
+/// being reshaped is never briefly revoked, and nothing outside the two sets is touched.
+fn acl_diff_ops(current: &BTreeSet<resource::KafkaAcl>, desired: &BTreeSet<resource::KafkaAcl>) -> Vec<PlanResponseElement> {
+    let mut ops = Vec::new();
+
+    for acl in desired.difference(current) {
+        ops.push(connector_op!(
+            KafkaConnectorOp::CreateAcl(acl.clone()),
+            format!("Create ACL: {}", acl.describe())
+        ));
+    }
+    for acl in current.difference(desired) {
+        ops.push(connector_op!(
+            KafkaConnectorOp::DeleteAcl(acl.clone()),
+            format!("Delete ACL: {}", acl.describe())
+        ));
+    }
+
+    ops
+}
+
 // Helper to convert KafkaResource into specific types
-impl From<resource::KafkaResource> for resource::KafkaTopic {
+impl From<resource::KafkaResource> for resource::KafkaTopicSpec {
     fn from(res: resource::KafkaResource) -> Self {
         match res {
             resource::KafkaResource::Topic(t) => t,
@@ -219,7 +700,16 @@ impl From<resource::KafkaResource> for resource::KafkaTopic {
     }
 }
 
-impl From<resource::KafkaResource> for resource::KafkaAcl {
+impl From<resource::KafkaResource> for resource::KafkaSharedTopic {
+    fn from(res: resource::KafkaResource) -> Self {
+        match res {
+            resource::KafkaResource::SharedTopic(t) => t,
+            _ => panic!("Expected SharedTopic resource"),
+        }
+    }
+}
+
+impl From<resource::KafkaResource> for resource::KafkaAclSet {
     fn from(res: resource::KafkaResource) -> Self {
         match res {
             resource::KafkaResource::Acl(a) => a,
@@ -228,6 +718,15 @@ impl From<resource::KafkaResource> for resource::KafkaAcl {
     }
 }
 
+impl From<resource::KafkaResource> for resource::KafkaAccess {
+    fn from(res: resource::KafkaResource) -> Self {
+        match res {
+            resource::KafkaResource::Access(a) => a,
+            _ => panic!("Expected Access resource"),
+        }
+    }
+}
+
 impl From<resource::KafkaResource> for resource::KafkaQuota {
     fn from(res: resource::KafkaResource) -> Self {
         match res {
@@ -0,0 +1,242 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, anyhow, bail};
+use autoschematic_core::{
+    connector::{ConnectorOp, ResourceAddress, TaskExecResponse},
+    util::{PrettyConfig, RON},
+};
+use futures_util::future::try_join_all;
+use rdkafka::{consumer::Consumer, producer::FutureRecord, util::Timeout};
+use rdkafka_autoschematic_fork as rdkafka;
+use serde::Serialize;
+
+use crate::{
+    KafkaConnector,
+    addr::KafkaResourceAddress,
+    archive::{self, KafkaArchiveManifest},
+    client::{create_consumer, create_producer},
+    op::KafkaConnectorOp,
+    task::{
+        KafkaArchiveTopicTask, KafkaCopyTopicTask, KafkaEffectiveAccessTask, KafkaOrphanAuditTask, KafkaRestoreTopicTask,
+        KafkaTask,
+    },
+};
+
+/// Records sent to the producer's queue before waiting for their delivery
+const RESTORE_BATCH_SIZE: usize = 1000;
+
+impl KafkaConnector {
+    pub async fn do_task_exec(
+        &self,
+        addr: &Path,
+        body: Vec<u8>,
+        state: Option<Vec<u8>>,
+    ) -> anyhow::Result<TaskExecResponse> {
+        let addr = KafkaResourceAddress::from_path(addr)?;
+
+        let KafkaResourceAddress::Task { kind } = addr else {
+            bail!("Not a task: {}", addr.to_path_buf().display());
+        };
+
+        let body = std::str::from_utf8(&body)?;
+
+        let friendly_message = match kind {
+            KafkaTask::ArchiveTopic => {
+                let task: KafkaArchiveTopicTask = RON.from_str(body)?;
+                let (path, manifest) = self.archive_topic(&task.cluster, &task.topic, &task.archive_dir).await?;
+                format!(
+                    "Archived {} records from topic '{}' in cluster '{}' to {}",
+                    manifest.partitions.iter().map(|p| p.records).sum::<u64>(),
+                    task.topic,
+                    task.cluster,
+                    path.display()
+                )
+            }
+            KafkaTask::RestoreTopic => {
+                let task: KafkaRestoreTopicTask = RON.from_str(body)?;
+                let records = self.restore_topic(&task).await?;
+                format!(
+                    "Restored {} records from {} into topic '{}' in cluster '{}'",
+                    records, task.archive_path, task.topic, task.cluster
+                )
+            }
+            KafkaTask::CopyTopic => {
+                let task: KafkaCopyTopicTask = RON.from_str(body)?;
+                return self.copy_topic(&task, state).await;
+            }
+            KafkaTask::EffectiveAccess => {
+                let task: KafkaEffectiveAccessTask = RON.from_str(body)?;
+                self.effective_access_task(&task).await?
+            }
+            KafkaTask::AuditOrphans => {
+                let task: KafkaOrphanAuditTask = RON.from_str(body)?;
+                self.audit_orphans_task(&task).await?
+            }
+        };
+
+        Ok(TaskExecResponse {
+            friendly_message: Some(friendly_message),
+            ..Default::default()
+        })
+    }
+
+    /// Resolve a path from a task or the config, relative to the repository root if not absolute
+    pub fn resolve_path(&self, path: &str) -> PathBuf {
+        let path = Path::new(path);
+        if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            self.prefix.join(path)
+        }
+    }
+
+    /// Write a task's report as RON, at a path resolved like `resolve_path`
+    pub fn write_report(&self, report_path: &str, report: &impl Serialize) -> anyhow::Result<()> {
+        let path = self.resolve_path(report_path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let s = RON.to_string_pretty(report, PrettyConfig::default())?;
+        std::fs::write(&path, s).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Copy every record in a topic into a new archive under `archive_dir`.
+    /// See the `archive` module for the layout.
+    pub async fn archive_topic(
+        &self,
+        cluster: &str,
+        topic: &str,
+        archive_dir: &str,
+    ) -> anyhow::Result<(PathBuf, KafkaArchiveManifest)> {
+        let Some(definition) = self.fetch_topic(cluster, topic).await? else {
+            bail!("Topic '{}' not found in cluster '{}'", topic, cluster);
+        };
+
+        let (cluster_config, timeout) = self.cluster_config(cluster).await?;
+        let archive_dir = self.resolve_path(archive_dir);
+        let _permit = self.permit().await?;
+
+        let prefix = self.prefix.clone();
+        let cluster_name = cluster.to_string();
+        let topic_name = topic.to_string();
+        tokio::task::spawn_blocking(move || {
+            let consumer = create_consumer(&prefix, &cluster_config, None)?;
+            archive::write_archive(&consumer, &cluster_name, &topic_name, definition, &archive_dir, timeout)
+        })
+        .await?
+        .with_context(|| format!("Failed to archive topic '{}' in cluster '{}'", topic, cluster))
+    }
+
+    /// Produce an archive back into a topic, creating it from the archived definition if it
+    /// doesn't exist. Records keep their partition where the target topic has it, along with
+    /// their timestamps and headers. Returns the number of records produced.
+    async fn restore_topic(&self, task: &KafkaRestoreTopicTask) -> anyhow::Result<u64> {
+        let cluster = &task.cluster;
+        let topic = &task.topic;
+
+        self.check_cluster_mode(cluster, false).await?;
+
+        if self.config.read().await.validate_only {
+            bail!(
+                "validate_only is set in kafka/config.ron; not restoring into topic '{}'",
+                topic
+            );
+        }
+
+        let archive_path = self.resolve_path(&task.archive_path);
+        let manifest = archive::read_manifest(&archive_path)?;
+
+        let partitions = match self.fetch_topic(cluster, topic).await? {
+            Some(existing) => {
+                // Producing into a topic that already has data would interleave or duplicate records
+                let (cluster_config, timeout) = self.cluster_config(cluster).await?;
+                let prefix = self.prefix.clone();
+                let topic_name = topic.clone();
+                let has_records = tokio::task::spawn_blocking(move || {
+                    let consumer = create_consumer(&prefix, &cluster_config, None)?;
+                    for partition in 0..existing.partitions {
+                        let (low, high) = consumer.fetch_watermarks(&topic_name, partition, timeout)?;
+                        if high > low {
+                            return Ok(true);
+                        }
+                    }
+                    Ok::<_, anyhow::Error>(false)
+                })
+                .await??;
+
+                if has_records {
+                    bail!(
+                        "Topic '{}' in cluster '{}' already contains records; restore into an empty or new topic",
+                        topic,
+                        cluster
+                    );
+                }
+                existing.partitions
+            }
+            None => {
+                let topic_addr = KafkaResourceAddress::Topic {
+                    cluster: cluster.clone(),
+                    topic: topic.clone(),
+                };
+                let op = KafkaConnectorOp::CreateTopic(manifest.definition.clone());
+                self.do_op_exec(&topic_addr.to_path_buf(), &op.to_string()?).await?;
+                manifest.definition.partitions
+            }
+        };
+
+        let (cluster_config, timeout) = self.cluster_config(cluster).await?;
+        let producer = &create_producer(&self.prefix, &cluster_config)?;
+
+        let mut restored = 0;
+        for archived_partition in &manifest.partitions {
+            let mut records = archive::read_partition(&archive_path, archived_partition.partition)?.peekable();
+
+            while records.peek().is_some() {
+                let batch = records
+                    .by_ref()
+                    .take(RESTORE_BATCH_SIZE)
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+
+                let mut decoded = Vec::with_capacity(batch.len());
+                for record in &batch {
+                    decoded.push((record.key_bytes()?, record.value_bytes()?, record.owned_headers()?));
+                }
+
+                let sends = batch.iter().zip(decoded.iter()).map(|(record, (key, value, headers))| {
+                    let mut future_record = FutureRecord::<[u8], [u8]>::to(topic).headers(headers.clone());
+                    if let Some(key) = key {
+                        future_record = future_record.key(key.as_slice());
+                    }
+                    if let Some(value) = value {
+                        future_record = future_record.payload(value.as_slice());
+                    }
+                    if let Some(timestamp) = record.timestamp {
+                        future_record = future_record.timestamp(timestamp);
+                    }
+                    if record.partition < partitions {
+                        future_record = future_record.partition(record.partition);
+                    }
+
+                    async move {
+                        producer
+                            .send(future_record, Timeout::After(timeout))
+                            .await
+                            .map_err(|(e, _)| anyhow!("Failed to produce record at offset {}: {}", record.offset, e))
+                    }
+                });
+
+                try_join_all(sends).await.with_context(|| {
+                    format!(
+                        "Failed to restore partition {} into topic '{}' after {} records",
+                        archived_partition.partition, topic, restored
+                    )
+                })?;
+
+                restored += batch.len() as u64;
+            }
+        }
+
+        Ok(restored)
+    }
+}
@@ -0,0 +1,112 @@
+//! Consumer group offset admin requests. rust-rdkafka doesn't wrap librdkafka's
+//! ListConsumerGroupOffsets, so it is called here on the admin client's native handle, in the
+//! same way as the ACL requests. Each request blocks until the brokers respond or the timeout
+//! passes, so callers run it with spawn_blocking.
+
+use std::ffi::c_char;
+use std::time::Duration;
+
+use anyhow::bail;
+use rdkafka::TopicPartitionList;
+use rdkafka::bindings as rdsys;
+use rdkafka::bindings::{
+    rd_kafka_AdminOptions_t, rd_kafka_event_t, rd_kafka_group_result_t, rd_kafka_queue_t, rd_kafka_t,
+    rd_kafka_topic_partition_list_t,
+};
+use rdkafka::types::RDKafkaRespErr;
+use rdkafka_autoschematic_fork as rdkafka;
+
+use crate::acl::{cstring, error_message, request};
+use crate::client::KafkaAdminClient;
+
+#[repr(C)]
+struct ListConsumerGroupOffsets {
+    _private: [u8; 0],
+}
+
+unsafe extern "C" {
+    fn rd_kafka_ListConsumerGroupOffsets_new(
+        group_id: *const c_char,
+        partitions: *const rd_kafka_topic_partition_list_t,
+    ) -> *mut ListConsumerGroupOffsets;
+    fn rd_kafka_ListConsumerGroupOffsets_destroy(list_grpoffsets: *mut ListConsumerGroupOffsets);
+    fn rd_kafka_ListConsumerGroupOffsets(
+        rk: *mut rd_kafka_t,
+        list_grpoffsets: *mut *mut ListConsumerGroupOffsets,
+        list_grpoffsets_cnt: usize,
+        options: *const rd_kafka_AdminOptions_t,
+        rkqu: *mut rd_kafka_queue_t,
+    );
+    fn rd_kafka_event_ListConsumerGroupOffsets_result(rkev: *mut rd_kafka_event_t) -> *const rd_kafka_event_t;
+    fn rd_kafka_ListConsumerGroupOffsets_result_groups(
+        result: *const rd_kafka_event_t,
+        cntp: *mut usize,
+    ) -> *const *const rd_kafka_group_result_t;
+}
+
+/// Owns the group and partitions handed to a request
+struct NativeRequest(*mut ListConsumerGroupOffsets);
+
+impl Drop for NativeRequest {
+    fn drop(&mut self) {
+        unsafe { rd_kafka_ListConsumerGroupOffsets_destroy(self.0) };
+    }
+}
+
+/// Whether a consumer group has a committed offset on any of a topic's partitions.
+/// librdkafka only accepts one group per request.
+pub fn has_committed_offsets(
+    client: &KafkaAdminClient,
+    group: &str,
+    topic: &str,
+    partitions: &[i32],
+    timeout: Duration,
+) -> anyhow::Result<bool> {
+    if partitions.is_empty() {
+        return Ok(false);
+    }
+
+    let group_id = cstring(group)?;
+    let mut tpl = TopicPartitionList::new();
+    for partition in partitions {
+        tpl.add_partition(topic, *partition);
+    }
+
+    let mut list = NativeRequest(unsafe { rd_kafka_ListConsumerGroupOffsets_new(group_id.as_ptr(), tpl.ptr()) });
+
+    let event = request(client, timeout, |rk, options, queue| unsafe {
+        rd_kafka_ListConsumerGroupOffsets(rk, &mut list.0, 1, options, queue)
+    })?;
+
+    unsafe {
+        let result = rd_kafka_event_ListConsumerGroupOffsets_result(event.0);
+        if result.is_null() {
+            bail!("Unexpected result event for ListConsumerGroupOffsets");
+        }
+
+        let mut count = 0;
+        let groups = rd_kafka_ListConsumerGroupOffsets_result_groups(result, &mut count);
+        for i in 0..count {
+            let group_result = *groups.add(i);
+            if let Some(message) = error_message(rdsys::rd_kafka_group_result_error(group_result)) {
+                bail!("Failed to list offsets of consumer group '{}': {}", group, message);
+            }
+
+            let offsets = rdsys::rd_kafka_group_result_partitions(group_result);
+            if offsets.is_null() {
+                continue;
+            }
+
+            // Partitions without a committed offset come back with an invalid (negative) offset
+            let elems = std::slice::from_raw_parts((*offsets).elems, (*offsets).cnt.max(0) as usize);
+            if elems
+                .iter()
+                .any(|e| e.err == RDKafkaRespErr::RD_KAFKA_RESP_ERR_NO_ERROR && e.offset >= 0)
+            {
+                return Ok(true);
+            }
+        }
+    }
+
+    Ok(false)
+}
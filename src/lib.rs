@@ -1,9 +1,19 @@
+pub mod acl;
+pub mod acl_state;
 pub mod addr;
+pub mod archive;
 pub mod client;
 pub mod config;
 pub mod connector;
+pub mod group_offsets;
+pub mod lifecycle;
 pub mod op;
+pub mod policy;
+pub mod profile;
 pub mod resource;
+pub mod shared_state;
+pub mod state_file;
 pub mod task;
+pub mod units;
 
 pub use connector::KafkaConnector;
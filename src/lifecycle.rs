@@ -0,0 +1,51 @@
+use std::path::Path;
+
+use autoschematic_core::connector::{OutputMapExec, ResourceAddress};
+
+use crate::{addr::KafkaResourceAddress, resource::KafkaTopicLifecycle};
+
+// A topic's lifecycle markers are kept in its outputs, which outlive the topic file, so deleting
+// the file can't also change how the topic may be deleted. A topic with no outputs recorded has
+// no markers set. Shared topics keep one set of markers per cluster, under "{cluster}.{key}".
+
+const PREVENT_DESTROY: &str = "prevent_destroy";
+const FORCE_DELETE: &str = "force_delete";
+
+fn key(cluster: Option<&str>, name: &str) -> String {
+    match cluster {
+        Some(cluster) => format!("{}.{}", cluster, name),
+        None => name.to_string(),
+    }
+}
+
+/// The outputs that record a topic's lifecycle markers. Unset markers are written as "false"
+/// rather than removed, since removing a resource's last output doesn't remove its output file.
+pub fn outputs(lifecycle: &KafkaTopicLifecycle) -> OutputMapExec {
+    [
+        (PREVENT_DESTROY, lifecycle.prevent_destroy),
+        (FORCE_DELETE, lifecycle.force_delete),
+    ]
+    .into_iter()
+    .map(|(name, set)| (name.to_string(), Some(set.to_string())))
+    .collect()
+}
+
+/// Move a topic's lifecycle outputs under a cluster, for a shared topic
+pub fn scoped_outputs(cluster: &str, outputs: OutputMapExec) -> OutputMapExec {
+    outputs
+        .into_iter()
+        .map(|(name, value)| (key(Some(cluster), &name), value))
+        .collect()
+}
+
+/// The lifecycle markers recorded in a topic's outputs, or a shared topic's for one cluster
+pub fn recorded(prefix: &Path, addr: &KafkaResourceAddress, cluster: Option<&str>) -> anyhow::Result<KafkaTopicLifecycle> {
+    let is_set = |name: &str| -> anyhow::Result<bool> {
+        Ok(addr.get_output(prefix, &key(cluster, name))?.as_deref() == Some("true"))
+    };
+
+    Ok(KafkaTopicLifecycle {
+        prevent_destroy: is_set(PREVENT_DESTROY)?,
+        force_delete: is_set(FORCE_DELETE)?,
+    })
+}
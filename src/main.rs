@@ -1,13 +1,23 @@
 use autoschematic_core::tarpc_bridge::tarpc_connector_main;
 use connector::KafkaConnector;
 
+pub mod acl;
+pub mod acl_state;
 pub mod addr;
+pub mod archive;
 pub mod client;
 pub mod config;
 pub mod connector;
+pub mod group_offsets;
+pub mod lifecycle;
 pub mod op;
+pub mod policy;
+pub mod profile;
 pub mod resource;
+pub mod shared_state;
+pub mod state_file;
 pub mod task;
+pub mod units;
 
 #[tokio::main]
 pub async fn main() -> anyhow::Result<()> {
@@ -4,15 +4,26 @@ use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-use super::resource::{KafkaAcl, KafkaQuota, KafkaTopic};
+use super::resource::{KafkaAcl, KafkaQuota, KafkaTopic, KafkaTopicLifecycle};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum KafkaConnectorOp {
+    // Config operations
+    ReloadConfig,
+
     // Topic operations
     CreateTopic(KafkaTopic),
     UpdateTopicPartitions { partitions: i32 },
     UpdateTopicConfig { config: IndexMap<String, String> },
-    DeleteTopic,
+    UpdateTopicLifecycle(KafkaTopicLifecycle),
+    /// `force` skips the pre-delete checks for consumer groups and recent writes
+    DeleteTopic { force: bool },
+
+    /// An op for one cluster, planned from a shared topic definition
+    ApplyToCluster {
+        cluster: String,
+        op: Box<KafkaConnectorOp>,
+    },
 
     // ACL operations
     CreateAcl(KafkaAcl),
@@ -24,6 +35,19 @@ pub enum KafkaConnectorOp {
     DeleteQuota,
 }
 
+impl KafkaConnectorOp {
+    /// Whether this op destroys state, and so is rejected on protected clusters
+    pub fn is_destructive(&self) -> bool {
+        match self {
+            KafkaConnectorOp::ApplyToCluster { op, .. } => op.is_destructive(),
+            _ => matches!(
+                self,
+                KafkaConnectorOp::DeleteTopic { .. } | KafkaConnectorOp::DeleteAcl(_) | KafkaConnectorOp::DeleteQuota
+            ),
+        }
+    }
+}
+
 impl ConnectorOp for KafkaConnectorOp {
     fn to_string(&self) -> Result<String, anyhow::Error> {
         Ok(RON.to_string(self)?)
@@ -0,0 +1,390 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, bail};
+use autoschematic_core::{
+    diag::{Diagnostic, DiagnosticPosition, DiagnosticSeverity, DiagnosticSpan},
+    util::RON,
+};
+use documented::{Documented, DocumentedFields};
+use indexmap::IndexMap;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::resource::{KafkaConfigValue, KafkaTopic};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Documented, DocumentedFields)]
+#[serde(default, deny_unknown_fields)]
+/// Rules that topics must satisfy, at kafka/policy.ron for every cluster and
+/// kafka/{cluster}/policy.ron for one cluster. Both files apply to a cluster's topics.
+pub struct KafkaTopicPolicy {
+    /// The rules to check, each reported by name when violated
+    pub rules: Vec<KafkaPolicyRule>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Documented, DocumentedFields)]
+#[serde(deny_unknown_fields)]
+/// A named check, optionally limited to some topics
+pub struct KafkaPolicyRule {
+    /// Name reported when the rule is violated
+    pub name: String,
+    /// Only check topics whose name matches this regex (optional)
+    #[serde(default)]
+    pub topic_pattern: Option<String>,
+    /// Only check topics with these config values. For list settings such as `cleanup.policy`,
+    /// any one of the topic's values can match (optional)
+    #[serde(default)]
+    pub when_config: IndexMap<String, KafkaConfigValue>,
+    /// What the rule requires
+    pub check: KafkaPolicyCheck,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Documented, DocumentedFields)]
+/// A requirement on a topic's name, partitions, replication factor or config
+pub enum KafkaPolicyCheck {
+    /// The topic name must match this regex, e.g. `^[a-z]+\.[a-z_]+\.v[0-9]+$`
+    NameMatches(String),
+    /// These config keys must be set on the topic
+    RequiredConfig(Vec<String>),
+    /// A config value must be within bounds. Bounds accept units like `30d` or `1GiB`, and
+    /// -1 (unlimited) exceeds any maximum. Topics that don't set the key are not checked.
+    ConfigRange {
+        key: String,
+        #[serde(default)]
+        min: Option<KafkaConfigValue>,
+        #[serde(default)]
+        max: Option<KafkaConfigValue>,
+    },
+    /// A config value, if set, must be one of these
+    ConfigAllowed { key: String, values: Vec<KafkaConfigValue> },
+    /// The partition count must be within bounds
+    Partitions {
+        #[serde(default)]
+        min: Option<i32>,
+        #[serde(default)]
+        max: Option<i32>,
+    },
+    /// The replication factor must be within bounds
+    ReplicationFactor {
+        #[serde(default)]
+        min: Option<i16>,
+        #[serde(default)]
+        max: Option<i16>,
+    },
+}
+
+/// A rule a topic breaks
+#[derive(Debug, Clone)]
+pub struct KafkaPolicyViolation {
+    pub rule: String,
+    pub message: String,
+    /// The setting the violation is about, used to point diagnostics at it
+    pub field: Option<String>,
+}
+
+impl KafkaTopicPolicy {
+    pub fn path(prefix: &Path, cluster: Option<&str>) -> PathBuf {
+        match cluster {
+            Some(cluster) => prefix.join("kafka").join(cluster).join("policy.ron"),
+            None => prefix.join("kafka").join("policy.ron"),
+        }
+    }
+
+    /// The global rules followed by the cluster's own rules
+    pub fn load(prefix: &Path, cluster: &str) -> anyhow::Result<Self> {
+        let mut policy = Self::default();
+
+        for path in [Self::path(prefix, None), Self::path(prefix, Some(cluster))] {
+            if !path.exists() {
+                continue;
+            }
+
+            let s = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+            let file: Self = RON
+                .from_str(&s)
+                .with_context(|| format!("Failed to parse {}", path.display()))?;
+            policy.rules.extend(file.rules);
+        }
+
+        Ok(policy)
+    }
+
+    pub fn evaluate(&self, topic_name: &str, topic: &KafkaTopic) -> anyhow::Result<Vec<KafkaPolicyViolation>> {
+        let wire_config = topic.wire_config()?;
+        let mut violations = Vec::new();
+
+        for rule in &self.rules {
+            if !rule.applies_to(topic_name, &wire_config)? {
+                continue;
+            }
+
+            let violation = |message: String, field: Option<&str>| KafkaPolicyViolation {
+                rule: rule.name.clone(),
+                message,
+                field: field.map(String::from),
+            };
+
+            match &rule.check {
+                KafkaPolicyCheck::NameMatches(pattern) => {
+                    if !rule.regex(pattern)?.is_match(topic_name) {
+                        violations.push(violation(format!("topic name must match '{}'", pattern), None));
+                    }
+                }
+                KafkaPolicyCheck::RequiredConfig(keys) => {
+                    for key in keys {
+                        if !wire_config.contains_key(key) {
+                            violations.push(violation(format!("config '{}' must be set", key), None));
+                        }
+                    }
+                }
+                KafkaPolicyCheck::ConfigRange { key, min, max } => {
+                    let Some(value) = wire_config.get(key) else {
+                        continue;
+                    };
+                    let value: i64 = value
+                        .parse()
+                        .with_context(|| format!("Config '{}' is not a number, as rule '{}' requires", key, rule.name))?;
+
+                    if let Some(max) = max {
+                        let limit: i64 = max.to_wire(key)?.parse()?;
+                        if value == -1 || value > limit {
+                            violations.push(violation(
+                                format!("config '{}' must be at most {}", key, bound_text(max)),
+                                Some(key),
+                            ));
+                        }
+                    }
+                    if let Some(min) = min {
+                        let limit: i64 = min.to_wire(key)?.parse()?;
+                        if value != -1 && value < limit {
+                            violations.push(violation(
+                                format!("config '{}' must be at least {}", key, bound_text(min)),
+                                Some(key),
+                            ));
+                        }
+                    }
+                }
+                KafkaPolicyCheck::ConfigAllowed { key, values } => {
+                    let Some(value) = wire_config.get(key) else {
+                        continue;
+                    };
+                    let allowed = values.iter().map(|v| v.to_wire(key)).collect::<anyhow::Result<Vec<_>>>()?;
+
+                    if !allowed.contains(value) {
+                        violations.push(violation(
+                            format!("config '{}' must be one of: {}", key, allowed.join(", ")),
+                            Some(key),
+                        ));
+                    }
+                }
+                KafkaPolicyCheck::Partitions { min, max } => {
+                    if let Some(max) = max
+                        && topic.partitions > *max
+                    {
+                        violations.push(violation(format!("partitions must be at most {}", max), Some("partitions")));
+                    }
+                    if let Some(min) = min
+                        && topic.partitions < *min
+                    {
+                        violations.push(violation(format!("partitions must be at least {}", min), Some("partitions")));
+                    }
+                }
+                KafkaPolicyCheck::ReplicationFactor { min, max } => {
+                    if let Some(max) = max
+                        && topic.replication_factor > *max
+                    {
+                        violations.push(violation(
+                            format!("replication_factor must be at most {}", max),
+                            Some("replication_factor"),
+                        ));
+                    }
+                    if let Some(min) = min
+                        && topic.replication_factor < *min
+                    {
+                        violations.push(violation(
+                            format!("replication_factor must be at least {}", min),
+                            Some("replication_factor"),
+                        ));
+                    }
+                }
+            }
+        }
+
+        Ok(violations)
+    }
+}
+
+/// A bound as written in the policy, e.g. `30d` rather than its value in milliseconds
+fn bound_text(value: &KafkaConfigValue) -> String {
+    match value {
+        KafkaConfigValue::Bool(b) => b.to_string(),
+        KafkaConfigValue::Int(i) => i.to_string(),
+        KafkaConfigValue::String(s) => s.clone(),
+    }
+}
+
+impl KafkaPolicyRule {
+    fn regex(&self, pattern: &str) -> anyhow::Result<Regex> {
+        Regex::new(pattern).with_context(|| format!("Invalid regex '{}' in policy rule '{}'", pattern, self.name))
+    }
+
+    fn applies_to(&self, topic_name: &str, wire_config: &IndexMap<String, String>) -> anyhow::Result<bool> {
+        if let Some(pattern) = &self.topic_pattern
+            && !self.regex(pattern)?.is_match(topic_name)
+        {
+            return Ok(false);
+        }
+
+        for (key, expected) in &self.when_config {
+            let expected = expected.to_wire(key)?;
+            let matches = wire_config
+                .get(key)
+                .is_some_and(|value| value.split(',').any(|v| v.trim() == expected));
+            if !matches {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+/// Fail if a topic breaks any of its cluster's policy rules, naming each rule it breaks
+pub fn check_topic(prefix: &Path, cluster: &str, topic_name: &str, topic: &KafkaTopic) -> anyhow::Result<()> {
+    let violations = KafkaTopicPolicy::load(prefix, cluster)?.evaluate(topic_name, topic)?;
+
+    if !violations.is_empty() {
+        let report: Vec<String> = violations.iter().map(|v| format!("[{}] {}", v.rule, v.message)).collect();
+        bail!(
+            "Topic '{}' in cluster '{}' violates policy: {}",
+            topic_name,
+            cluster,
+            report.join("; ")
+        );
+    }
+
+    Ok(())
+}
+
+/// Policy violations as diagnostics on a topic file, placed on the offending setting where
+/// it appears in the file and on the first line otherwise
+pub fn topic_diagnostics(
+    prefix: &Path,
+    cluster: &str,
+    topic_name: &str,
+    topic: &KafkaTopic,
+    s: &str,
+) -> anyhow::Result<Vec<Diagnostic>> {
+    let violations = KafkaTopicPolicy::load(prefix, cluster)?.evaluate(topic_name, topic)?;
+
+    Ok(violations
+        .into_iter()
+        .map(|violation| {
+            let (line, col, len) = violation
+                .field
+                .as_deref()
+                .and_then(|field| {
+                    s.lines()
+                        .enumerate()
+                        .find_map(|(i, line)| line.find(field).map(|col| (i, col, line.len())))
+                })
+                .unwrap_or((0, 0, s.lines().next().map(str::len).unwrap_or(0)));
+
+            Diagnostic {
+                severity: DiagnosticSeverity::ERROR as u8,
+                span: DiagnosticSpan {
+                    start: DiagnosticPosition {
+                        line: line as u32 + 1,
+                        col: col as u32 + 1,
+                    },
+                    end: DiagnosticPosition {
+                        line: line as u32 + 1,
+                        col: len as u32 + 1,
+                    },
+                },
+                message: format!("Policy rule '{}': {}", violation.rule, violation.message),
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_policy(s: &str) -> KafkaTopicPolicy {
+        RON.from_str(s).unwrap()
+    }
+
+    fn topic(config: &[(&str, KafkaConfigValue)]) -> KafkaTopic {
+        KafkaTopic {
+            partitions: 3,
+            replication_factor: 3,
+            config: config.iter().map(|(k, v)| (k.to_string(), v.clone())).collect(),
+            lifecycle: Default::default(),
+        }
+    }
+
+    const RETENTION_RANGE: &str =
+        r#"(rules: [(name: "retention", check: ConfigRange(key: "retention.ms", min: Some("1d"), max: Some("30d")))])"#;
+
+    #[test]
+    fn unlimited_exceeds_any_maximum() {
+        let violations = parse_policy(RETENTION_RANGE)
+            .evaluate("orders", &topic(&[("retention.ms", KafkaConfigValue::Int(-1))]))
+            .unwrap();
+
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].message, "config 'retention.ms' must be at most 30d");
+        assert_eq!(violations[0].field.as_deref(), Some("retention.ms"));
+    }
+
+    #[test]
+    fn unlimited_satisfies_any_minimum() {
+        let policy =
+            parse_policy(r#"(rules: [(name: "retention", check: ConfigRange(key: "retention.ms", min: Some("1d")))])"#);
+
+        let violations = policy
+            .evaluate("orders", &topic(&[("retention.ms", KafkaConfigValue::String("-1".into()))]))
+            .unwrap();
+        assert!(violations.is_empty());
+    }
+
+    #[test]
+    fn compares_bounds_in_units() {
+        let policy = parse_policy(RETENTION_RANGE);
+
+        let within = topic(&[("retention.ms", KafkaConfigValue::String("7d".into()))]);
+        assert!(policy.evaluate("orders", &within).unwrap().is_empty());
+
+        let above = topic(&[("retention.ms", KafkaConfigValue::String("60d".into()))]);
+        assert_eq!(policy.evaluate("orders", &above).unwrap().len(), 1);
+
+        let below = topic(&[("retention.ms", KafkaConfigValue::Int(3_600_000))]);
+        let violations = policy.evaluate("orders", &below).unwrap();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].message, "config 'retention.ms' must be at least 1d");
+    }
+
+    #[test]
+    fn when_config_matches_any_list_value() {
+        let policy = parse_policy(
+            r#"(rules: [(
+                name: "deleting topics expire",
+                when_config: {"cleanup.policy": "delete"},
+                check: RequiredConfig(["retention.ms"]),
+            )])"#,
+        );
+
+        let both = topic(&[("cleanup.policy", KafkaConfigValue::String("compact, delete".into()))]);
+        assert_eq!(policy.evaluate("orders", &both).unwrap().len(), 1);
+
+        let delete = topic(&[("cleanup.policy", KafkaConfigValue::String("delete".into()))]);
+        assert_eq!(policy.evaluate("orders", &delete).unwrap().len(), 1);
+
+        let compact = topic(&[("cleanup.policy", KafkaConfigValue::String("compact".into()))]);
+        assert!(policy.evaluate("orders", &compact).unwrap().is_empty());
+
+        let unset = topic(&[]);
+        assert!(policy.evaluate("orders", &unset).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, bail};
+use autoschematic_core::{
+    diag::{Diagnostic, DiagnosticPosition, DiagnosticSeverity, DiagnosticSpan},
+    util::RON,
+};
+
+use crate::addr::SHARED_DIR;
+use crate::resource::{KafkaTopic, KafkaTopicProfile, KafkaTopicSpec};
+
+/// Directory holding profiles for `scope`, which is a cluster name or `_shared`
+fn profile_dir(prefix: &Path, scope: &str) -> PathBuf {
+    prefix.join("kafka").join(scope).join("profiles")
+}
+
+fn load_file(path: &Path) -> anyhow::Result<KafkaTopicProfile> {
+    let s = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(RON
+        .from_str(&s)
+        .with_context(|| format!("Failed to parse {}", path.display()))?)
+}
+
+/// Load a profile for a cluster. A profile in the cluster's own directory takes precedence
+/// over a shared one with the same name.
+pub fn load(prefix: &Path, cluster: &str, name: &str) -> anyhow::Result<KafkaTopicProfile> {
+    for scope in [cluster, SHARED_DIR] {
+        let path = profile_dir(prefix, scope).join(format!("{}.ron", name));
+        if path.exists() {
+            return load_file(&path);
+        }
+    }
+
+    bail!(
+        "Profile '{}' not found at kafka/{}/profiles/{}.ron or kafka/{}/profiles/{}.ron",
+        name,
+        cluster,
+        name,
+        SHARED_DIR,
+        name
+    )
+}
+
+/// Every profile available to a cluster, by name. Profiles that fail to parse are skipped with
+/// a warning, so that one bad file doesn't break describing every topic in the cluster.
+pub fn list(prefix: &Path, cluster: &str) -> anyhow::Result<BTreeMap<String, KafkaTopicProfile>> {
+    let mut profiles = BTreeMap::new();
+
+    // Shared first, so the cluster's own profiles replace them
+    for scope in [SHARED_DIR, cluster] {
+        let dir = profile_dir(prefix, scope);
+        if !dir.is_dir() {
+            continue;
+        }
+
+        for entry in std::fs::read_dir(&dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "ron")
+                && let Some(name) = path.file_stem().and_then(|s| s.to_str())
+            {
+                match load_file(&path) {
+                    Ok(profile) => {
+                        profiles.insert(name.to_string(), profile);
+                    }
+                    Err(e) => tracing::warn!("Skipping profile: {:#}", e),
+                }
+            }
+        }
+    }
+
+    Ok(profiles)
+}
+
+/// Topic files that name a profile, relative to the repository root. A shared profile is used
+/// by every cluster that has no profile of its own by that name.
+pub fn dependents(prefix: &Path, scope: &str, name: &str) -> anyhow::Result<Vec<PathBuf>> {
+    let clusters: Vec<String> = if scope == SHARED_DIR {
+        let kafka_dir = prefix.join("kafka");
+        let mut clusters = Vec::new();
+        if kafka_dir.is_dir() {
+            for entry in std::fs::read_dir(&kafka_dir).with_context(|| format!("Failed to read {}", kafka_dir.display()))? {
+                let path = entry?.path();
+                if path.is_dir()
+                    && let Some(cluster) = path.file_name().and_then(|s| s.to_str())
+                    && cluster != SHARED_DIR
+                    && !profile_dir(prefix, cluster).join(format!("{}.ron", name)).exists()
+                {
+                    clusters.push(cluster.to_string());
+                }
+            }
+        }
+        clusters
+    } else {
+        vec![scope.to_string()]
+    };
+
+    let mut topics = Vec::new();
+    for cluster in clusters {
+        let dir = prefix.join("kafka").join(&cluster).join("topics");
+        if !dir.is_dir() {
+            continue;
+        }
+
+        for entry in std::fs::read_dir(&dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "ron") {
+                continue;
+            }
+
+            // Unparsable topic files get their own diagnostics
+            let Ok(spec) = std::fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|s| Ok(RON.from_str::<KafkaTopicSpec>(&s)?))
+            else {
+                continue;
+            };
+
+            if spec.profile.as_deref() == Some(name)
+                && let Ok(relative) = path.strip_prefix(prefix)
+            {
+                topics.push(relative.to_path_buf());
+            }
+        }
+    }
+
+    topics.sort();
+    Ok(topics)
+}
+
+/// A warning on the profile's first line naming the topic files that use it, since changing
+/// the profile doesn't re-plan them
+pub fn diag_warnings(prefix: &Path, scope: &str, name: &str, s: &str) -> anyhow::Result<Vec<Diagnostic>> {
+    let topics = dependents(prefix, scope, name)?;
+    if topics.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let topics: Vec<String> = topics.iter().map(|path| path.display().to_string()).collect();
+    Ok(vec![Diagnostic {
+        severity: DiagnosticSeverity::WARNING as u8,
+        span: DiagnosticSpan {
+            start: DiagnosticPosition { line: 1, col: 1 },
+            end: DiagnosticPosition {
+                line: 1,
+                col: s.lines().next().map(str::len).unwrap_or(0) as u32 + 1,
+            },
+        },
+        message: format!(
+            "Changes to this profile only reach topics when their own files are planned; \
+             touch these in the same change to apply it: {}",
+            topics.join(", ")
+        ),
+    }])
+}
+
+/// Resolve a topic file against its profile, if it names one
+pub fn resolve_topic(prefix: &Path, cluster: &str, spec: &KafkaTopicSpec) -> anyhow::Result<KafkaTopic> {
+    let profile = match &spec.profile {
+        Some(name) => Some(load(prefix, cluster, name)?),
+        None => None,
+    };
+
+    Ok(spec.resolve(profile.as_ref()))
+}
+
+/// Describe a live topic by profile if one matches it exactly, and otherwise in full
+pub fn describe_topic(prefix: &Path, cluster: &str, topic: KafkaTopic) -> anyhow::Result<KafkaTopicSpec> {
+    let normalized = topic.normalized()?;
+
+    for (name, profile) in list(prefix, cluster)? {
+        let spec = KafkaTopicSpec {
+            profile: Some(name),
+            lifecycle: topic.lifecycle.clone(),
+            ..Default::default()
+        };
+
+        if spec.resolve(Some(&profile)).normalized()? == normalized {
+            return Ok(spec);
+        }
+    }
+
+    Ok(topic.into())
+}
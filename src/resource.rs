@@ -1,7 +1,9 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
+use anyhow::{Context, bail};
 use autoschematic_core::{
     connector::{Resource, ResourceAddress},
+    diag::{Diagnostic, DiagnosticPosition, DiagnosticSeverity, DiagnosticSpan},
     error_util::invalid_addr,
     macros::FieldTypes,
     util::RON,
@@ -9,9 +11,99 @@ use autoschematic_core::{
 use autoschematic_macros::FieldTypes;
 use documented::{Documented, DocumentedFields};
 use indexmap::IndexMap;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
 use super::addr::KafkaResourceAddress;
+use crate::units;
+
+/// Read an optional setting written either bare (`partitions: 3`), as files from before the
+/// setting became optional are, or as `Some(3)`
+fn bare_or_some<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    // Buffered untagged content reads any value other than None or Some(..) as Some(value)
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BareOrSome<T> {
+        Value(Option<T>),
+    }
+
+    let BareOrSome::Value(value) = BareOrSome::deserialize(deserializer)?;
+    Ok(value)
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Documented, DocumentedFields)]
+#[serde(untagged)]
+/// A topic configuration value. Durations (keys ending in `.ms`) accept units like `7d` or `12h`,
+/// and sizes (keys ending in `.bytes`) accept units like `1GiB` or `500MB`.
+pub enum KafkaConfigValue {
+    /// A boolean value, e.g. `true`
+    Bool(bool),
+    /// An integer value, e.g. `604800000`
+    Int(i64),
+    /// A string value, e.g. `"snappy"` or `"7d"`
+    String(String),
+}
+
+impl KafkaConfigValue {
+    /// Normalize this value to the string Kafka expects on the wire for the given key.
+    pub fn to_wire(&self, key: &str) -> anyhow::Result<String> {
+        match self {
+            KafkaConfigValue::Bool(b) => Ok(b.to_string()),
+            KafkaConfigValue::Int(i) => Ok(i.to_string()),
+            KafkaConfigValue::String(s) if units::is_duration_key(key) => Ok(units::parse_duration_ms(s)
+                .with_context(|| format!("Invalid duration for '{}'", key))?
+                .to_string()),
+            KafkaConfigValue::String(s) if units::is_size_key(key) => Ok(units::parse_size_bytes(s)
+                .with_context(|| format!("Invalid size for '{}'", key))?
+                .to_string()),
+            KafkaConfigValue::String(s) => Ok(s.clone()),
+        }
+    }
+
+    /// Build a value from a wire string, rendering known duration and size keys in human form.
+    pub fn from_wire(key: &str, value: &str) -> Self {
+        let human = value.parse::<i64>().ok().and_then(|n| {
+            if units::is_duration_key(key) {
+                units::render_duration_ms(n)
+            } else if units::is_size_key(key) {
+                units::render_size_bytes(n)
+            } else {
+                None
+            }
+        });
+
+        KafkaConfigValue::String(human.unwrap_or_else(|| value.to_string()))
+    }
+}
+
+impl From<&str> for KafkaConfigValue {
+    fn from(value: &str) -> Self {
+        KafkaConfigValue::String(value.to_string())
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Documented, DocumentedFields, FieldTypes)]
+#[serde(default, deny_unknown_fields)]
+/// Lifecycle settings that control how the connector may change a topic
+pub struct KafkaTopicLifecycle {
+    /// Refuse to plan deletion of this topic. The flag must be removed in a separate change
+    /// before the topic file can be deleted. The connector records the flag in the topic's
+    /// outputs when it's applied, so it still holds once the topic file is deleted.
+    pub prevent_destroy: bool,
+    /// Skip the consumer group and recent write checks when this topic is deleted. Like
+    /// prevent_destroy it's recorded when applied, so set it in a change before the one that
+    /// deletes the topic file.
+    pub force_delete: bool,
+}
+
+impl KafkaTopicLifecycle {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Documented, DocumentedFields, FieldTypes)]
 #[serde(default, deny_unknown_fields)]
@@ -22,7 +114,35 @@ pub struct KafkaTopic {
     /// Replication factor for the topic
     pub replication_factor: i16,
     /// Topic-level configuration properties
-    pub config: IndexMap<String, String>,
+    pub config: IndexMap<String, KafkaConfigValue>,
+    /// Lifecycle settings, such as deletion protection (optional)
+    #[serde(skip_serializing_if = "KafkaTopicLifecycle::is_default")]
+    pub lifecycle: KafkaTopicLifecycle,
+}
+
+impl KafkaTopic {
+    /// The topic config with every value normalized to Kafka's wire strings
+    pub fn wire_config(&self) -> anyhow::Result<IndexMap<String, String>> {
+        self.config
+            .iter()
+            .map(|(key, value)| Ok((key.clone(), value.to_wire(key)?)))
+            .collect()
+    }
+
+    /// A copy of this topic with every config value normalized to Kafka's wire strings,
+    /// so that `7d` and `604800000` compare equal.
+    pub fn normalized(&self) -> anyhow::Result<Self> {
+        Ok(Self {
+            partitions: self.partitions,
+            replication_factor: self.replication_factor,
+            config: self
+                .wire_config()?
+                .into_iter()
+                .map(|(key, value)| (key, KafkaConfigValue::String(value)))
+                .collect(),
+            lifecycle: self.lifecycle.clone(),
+        })
+    }
 }
 
 impl Default for KafkaTopic {
@@ -31,11 +151,218 @@ impl Default for KafkaTopic {
             partitions: 1,
             replication_factor: 1,
             config: IndexMap::new(),
+            lifecycle: KafkaTopicLifecycle::default(),
         }
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Documented, DocumentedFields)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Documented, DocumentedFields, FieldTypes)]
+#[serde(rename = "KafkaTopic", default, deny_unknown_fields)]
+/// A topic file as written at kafka/{cluster}/topics/{topic}.ron. Settings left out come from
+/// the topic's profile if it names one, and otherwise default to 1 partition and replication factor 1.
+pub struct KafkaTopicSpec {
+    /// Name of a profile at kafka/{cluster}/profiles/{name}.ron or kafka/_shared/profiles/{name}.ron (optional)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Number of partitions for the topic
+    #[serde(skip_serializing_if = "Option::is_none", deserialize_with = "bare_or_some")]
+    pub partitions: Option<i32>,
+    /// Replication factor for the topic
+    #[serde(skip_serializing_if = "Option::is_none", deserialize_with = "bare_or_some")]
+    pub replication_factor: Option<i16>,
+    /// Topic-level configuration properties, added to or replacing the profile's
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    pub config: IndexMap<String, KafkaConfigValue>,
+    /// Lifecycle settings, such as deletion protection (optional)
+    #[serde(skip_serializing_if = "KafkaTopicLifecycle::is_default")]
+    pub lifecycle: KafkaTopicLifecycle,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Documented, DocumentedFields, FieldTypes)]
+#[serde(default, deny_unknown_fields)]
+/// Default settings for topics that name this profile, at kafka/{cluster}/profiles/{name}.ron
+/// or, for every cluster, kafka/_shared/profiles/{name}.ron. Topics pick up changes to a profile
+/// only when their own files are planned, so touch them in the same change.
+pub struct KafkaTopicProfile {
+    /// Number of partitions for the topic (optional)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partitions: Option<i32>,
+    /// Replication factor for the topic (optional)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replication_factor: Option<i16>,
+    /// Topic-level configuration properties
+    pub config: IndexMap<String, KafkaConfigValue>,
+}
+
+impl KafkaTopicSpec {
+    /// The topic this file describes, with its profile's settings filled in where it leaves them out
+    pub fn resolve(&self, profile: Option<&KafkaTopicProfile>) -> KafkaTopic {
+        let defaults = KafkaTopic::default();
+
+        let mut config = profile.map(|p| p.config.clone()).unwrap_or_default();
+        config.extend(self.config.iter().map(|(k, v)| (k.clone(), v.clone())));
+
+        KafkaTopic {
+            partitions: self
+                .partitions
+                .or(profile.and_then(|p| p.partitions))
+                .unwrap_or(defaults.partitions),
+            replication_factor: self
+                .replication_factor
+                .or(profile.and_then(|p| p.replication_factor))
+                .unwrap_or(defaults.replication_factor),
+            config,
+            lifecycle: self.lifecycle.clone(),
+        }
+    }
+}
+
+impl From<KafkaTopic> for KafkaTopicSpec {
+    fn from(topic: KafkaTopic) -> Self {
+        Self {
+            profile: None,
+            partitions: Some(topic.partitions),
+            replication_factor: Some(topic.replication_factor),
+            config: topic.config,
+            lifecycle: topic.lifecycle,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Documented, DocumentedFields, FieldTypes)]
+#[serde(default, deny_unknown_fields)]
+/// Settings for one cluster that differ from a shared topic definition
+pub struct KafkaTopicOverride {
+    /// Number of partitions on this cluster (optional)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partitions: Option<i32>,
+    /// Replication factor on this cluster (optional)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replication_factor: Option<i16>,
+    /// Configuration properties set or replaced on this cluster
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    pub config: IndexMap<String, KafkaConfigValue>,
+    /// Lifecycle settings on this cluster (optional)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lifecycle: Option<KafkaTopicLifecycle>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Documented, DocumentedFields, FieldTypes)]
+#[serde(default, deny_unknown_fields)]
+/// A topic defined once for several clusters, at kafka/_shared/topics/{topic}.ron.
+/// Each listed cluster gets the shared settings with its overrides applied on top.
+pub struct KafkaSharedTopic {
+    /// Number of partitions for the topic
+    pub partitions: i32,
+    /// Replication factor for the topic
+    pub replication_factor: i16,
+    /// Topic-level configuration properties
+    pub config: IndexMap<String, KafkaConfigValue>,
+    /// Lifecycle settings, such as deletion protection (optional)
+    #[serde(skip_serializing_if = "KafkaTopicLifecycle::is_default")]
+    pub lifecycle: KafkaTopicLifecycle,
+    /// The clusters the topic exists on, each with its overrides
+    pub clusters: BTreeMap<String, KafkaTopicOverride>,
+}
+
+impl Default for KafkaSharedTopic {
+    fn default() -> Self {
+        let topic = KafkaTopic::default();
+        Self {
+            partitions: topic.partitions,
+            replication_factor: topic.replication_factor,
+            config: topic.config,
+            lifecycle: topic.lifecycle,
+            clusters: BTreeMap::new(),
+        }
+    }
+}
+
+impl KafkaSharedTopic {
+    /// The topic as it should exist on one cluster, or None if the cluster isn't listed
+    pub fn resolve(&self, cluster: &str) -> Option<KafkaTopic> {
+        let overrides = self.clusters.get(cluster)?;
+
+        let mut config = self.config.clone();
+        config.extend(overrides.config.iter().map(|(k, v)| (k.clone(), v.clone())));
+
+        Some(KafkaTopic {
+            partitions: overrides.partitions.unwrap_or(self.partitions),
+            replication_factor: overrides.replication_factor.unwrap_or(self.replication_factor),
+            config,
+            lifecycle: overrides.lifecycle.clone().unwrap_or_else(|| self.lifecycle.clone()),
+        })
+    }
+
+    /// The topic as it should exist on every listed cluster
+    pub fn resolve_all(&self) -> BTreeMap<String, KafkaTopic> {
+        self.clusters
+            .keys()
+            .filter_map(|cluster| Some((cluster.clone(), self.resolve(cluster)?)))
+            .collect()
+    }
+
+    /// Combine a topic's state on several clusters into one definition. Settings shared by
+    /// the most clusters become the shared settings, config keys become shared only if every
+    /// cluster has the same value, and everything else becomes a per-cluster override.
+    pub fn from_clusters(topics: &BTreeMap<String, KafkaTopic>) -> Self {
+        fn most_common<T: PartialEq + Clone>(values: impl Iterator<Item = T>) -> Option<T> {
+            let mut counts: Vec<(T, usize)> = Vec::new();
+            for value in values {
+                match counts.iter_mut().find(|(v, _)| *v == value) {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((value, 1)),
+                }
+            }
+            // max_by_key returns the last maximum, so reverse to prefer the first cluster on ties
+            counts.into_iter().rev().max_by_key(|(_, count)| *count).map(|(v, _)| v)
+        }
+
+        let Some(first) = topics.values().next() else {
+            return Self::default();
+        };
+
+        let partitions = most_common(topics.values().map(|t| t.partitions)).unwrap_or(first.partitions);
+        let replication_factor =
+            most_common(topics.values().map(|t| t.replication_factor)).unwrap_or(first.replication_factor);
+        let lifecycle = most_common(topics.values().map(|t| t.lifecycle.clone())).unwrap_or_default();
+
+        let config: IndexMap<String, KafkaConfigValue> = first
+            .config
+            .iter()
+            .filter(|(key, value)| topics.values().all(|t| t.config.get(*key) == Some(*value)))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+
+        let clusters = topics
+            .iter()
+            .map(|(cluster, topic)| {
+                let overrides = KafkaTopicOverride {
+                    partitions: (topic.partitions != partitions).then_some(topic.partitions),
+                    replication_factor: (topic.replication_factor != replication_factor).then_some(topic.replication_factor),
+                    config: topic
+                        .config
+                        .iter()
+                        .filter(|(key, _)| !config.contains_key(*key))
+                        .map(|(key, value)| (key.clone(), value.clone()))
+                        .collect(),
+                    lifecycle: (topic.lifecycle != lifecycle).then(|| topic.lifecycle.clone()),
+                };
+                (cluster.clone(), overrides)
+            })
+            .collect();
+
+        Self {
+            partitions,
+            replication_factor,
+            config,
+            lifecycle,
+            clusters,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord, Documented, DocumentedFields)]
 /// The type of Kafka resource being controlled by the ACL
 pub enum KafkaResourceType {
     /// Topic resource type
@@ -46,38 +373,144 @@ pub enum KafkaResourceType {
     Cluster,
     /// Transactional ID resource type
     TransactionalId,
-    /// Delegation token resource type
+    /// Delegation token resource type. librdkafka can't send it, so bindings using it are rejected.
     DelegationToken,
+    /// User resource type, for delegation tokens created on behalf of other users (KIP-373).
+    /// librdkafka can't send it, so bindings using it are rejected.
+    User,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Documented, DocumentedFields)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord, Documented, DocumentedFields)]
 /// The pattern type for matching Kafka resources in ACLs
 pub enum KafkaResourcePatternType {
     /// Matches resources with the exact name
     Literal,
     /// Matches resources with names that have the specified prefix
     Prefixed,
+    /// In filters only: matches literal, wildcard and prefixed bindings that apply to a name
+    Match,
+    /// In filters only: matches bindings of any pattern type
+    Any,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Documented, DocumentedFields)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord, Documented, DocumentedFields)]
 /// The type of principal in a Kafka ACL
 pub enum KafkaPrincipalType {
-    /// A user principal
+    /// A user principal, `User:name`. Names from mTLS are the certificate's distinguished name,
+    /// e.g. `CN=foo,OU=bar`
     User,
-    /// A group principal
+    /// A group principal, `Group:name`
     Group,
+    /// A principal type defined by a custom principal builder, e.g. `Custom("Role")` for `Role:admin`
+    Custom(String),
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Documented, DocumentedFields)]
-/// A Kafka principal (user or group)
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord, Documented, DocumentedFields)]
+/// A Kafka principal, such as a user or group
 pub struct KafkaPrincipal {
     /// The type of principal
     pub principal_type: KafkaPrincipalType,
-    /// The name of the principal
+    /// The name of the principal, or `*` for every principal of the type
     pub name: String,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Documented, DocumentedFields)]
+impl KafkaPrincipal {
+    pub fn user(name: &str) -> Self {
+        Self {
+            principal_type: KafkaPrincipalType::User,
+            name: name.to_string(),
+        }
+    }
+
+    /// The principal as Kafka writes it, e.g. `User:alice`
+    pub fn to_wire(&self) -> String {
+        let principal_type = match &self.principal_type {
+            KafkaPrincipalType::User => "User",
+            KafkaPrincipalType::Group => "Group",
+            KafkaPrincipalType::Custom(principal_type) => principal_type,
+        };
+        format!("{}:{}", principal_type, self.name)
+    }
+
+    /// The principal named by an access file, e.g. `alice` for `User:alice`, or the full
+    /// principal for other types, e.g. `Group:admins`
+    pub fn from_file_stem(s: &str) -> anyhow::Result<Self> {
+        if s.contains(':') {
+            Self::from_wire(s)
+        } else {
+            Ok(Self::user(s))
+        }
+    }
+
+    pub fn to_file_stem(&self) -> String {
+        match &self.principal_type {
+            KafkaPrincipalType::User => self.name.clone(),
+            _ => self.to_wire(),
+        }
+    }
+
+    pub fn from_wire(s: &str) -> anyhow::Result<Self> {
+        let Some((principal_type, name)) = s.split_once(':') else {
+            bail!("Principal '{}' must be of the form 'User:name'", s);
+        };
+
+        let principal_type = match principal_type {
+            "User" => KafkaPrincipalType::User,
+            "Group" => KafkaPrincipalType::Group,
+            _ => KafkaPrincipalType::Custom(principal_type.to_string()),
+        };
+
+        let principal = Self {
+            principal_type,
+            name: name.to_string(),
+        };
+        principal.validate()?;
+        Ok(principal)
+    }
+
+    pub fn is_wildcard(&self) -> bool {
+        self.name == "*"
+    }
+
+    /// Check the principal is one Kafka can match. Names are otherwise free-form, since
+    /// custom principal builders and mTLS distinguished names may contain any characters.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if let KafkaPrincipalType::Custom(principal_type) = &self.principal_type
+            && (principal_type.is_empty() || !principal_type.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+        {
+            bail!(
+                "Principal type '{}' must be a non-empty name of letters, digits and underscores",
+                principal_type
+            );
+        }
+        if let KafkaPrincipalType::Custom(principal_type) = &self.principal_type
+            && (principal_type == "User" || principal_type == "Group")
+        {
+            bail!(
+                "Use the {} principal type rather than Custom(\"{}\")",
+                principal_type,
+                principal_type
+            );
+        }
+
+        if self.name.is_empty() {
+            bail!("Principal '{}' has an empty name", self.to_wire());
+        }
+        if self.name.trim() != self.name {
+            bail!("Principal '{}' has leading or trailing whitespace", self.to_wire());
+        }
+        if self.name.contains('*') && !self.is_wildcard() {
+            bail!(
+                "Principal '{}' uses '*' as part of a name; Kafka only supports '*' alone, matching every principal of a type",
+                self.to_wire()
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord, Documented, DocumentedFields)]
 /// The operation type for Kafka ACLs
 pub enum KafkaAclOperation {
     /// Read operation
@@ -102,9 +535,15 @@ pub enum KafkaAclOperation {
     IdempotentWrite,
     /// All operations
     All,
+    /// CreateTokens operation, for creating delegation tokens on behalf of a user.
+    /// librdkafka can't send it, so bindings using it are rejected.
+    CreateTokens,
+    /// DescribeTokens operation, for describing delegation tokens of a user.
+    /// librdkafka can't send it, so bindings using it are rejected.
+    DescribeTokens,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Documented, DocumentedFields)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord, Documented, DocumentedFields)]
 /// Permission type for Kafka ACLs
 pub enum KafkaAclPermission {
     /// Allow the operation
@@ -113,7 +552,9 @@ pub enum KafkaAclPermission {
     Deny,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Documented, DocumentedFields, FieldTypes)]
+#[derive(
+    Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord, Documented, DocumentedFields, FieldTypes,
+)]
 #[serde(deny_unknown_fields)]
 /// A Kafka Access Control List (ACL) entry
 pub struct KafkaAcl {
@@ -150,6 +591,363 @@ impl Default for KafkaAcl {
     }
 }
 
+impl KafkaAcl {
+    /// Check the binding is one Kafka can create
+    pub fn validate(&self) -> anyhow::Result<()> {
+        self.principal.validate()?;
+
+        if matches!(
+            self.pattern_type,
+            KafkaResourcePatternType::Match | KafkaResourcePatternType::Any
+        ) {
+            bail!(
+                "{:?} is only valid in filters; bindings use Literal or Prefixed: {}",
+                self.pattern_type,
+                self.describe()
+            );
+        }
+        if self.resource_name.is_empty() {
+            bail!("Binding has an empty resource name: {}", self.describe());
+        }
+        if self.resource_name == "*" && self.pattern_type != KafkaResourcePatternType::Literal {
+            bail!("The '*' wildcard resource must be Literal: {}", self.describe());
+        }
+        if self.resource_type == KafkaResourceType::Cluster
+            && (self.resource_name != CLUSTER_RESOURCE_NAME || self.pattern_type != KafkaResourcePatternType::Literal)
+        {
+            bail!(
+                "Cluster bindings must name the Literal resource '{}': {}",
+                CLUSTER_RESOURCE_NAME,
+                self.describe()
+            );
+        }
+        if self.host.is_empty() {
+            bail!("Binding has an empty host; use '*' for all hosts: {}", self.describe());
+        }
+        // librdkafka's admin API stops at TransactionalId and IdempotentWrite, and rejects the
+        // newer codes before the request reaches the brokers
+        if matches!(
+            self.resource_type,
+            KafkaResourceType::DelegationToken | KafkaResourceType::User
+        ) {
+            bail!(
+                "{:?} bindings can't be managed through librdkafka; use Kafka's own kafka-acls tool: {}",
+                self.resource_type,
+                self.describe()
+            );
+        }
+        if matches!(
+            self.operation,
+            KafkaAclOperation::CreateTokens | KafkaAclOperation::DescribeTokens
+        ) {
+            bail!(
+                "{:?} bindings can't be managed through librdkafka; use Kafka's own kafka-acls tool: {}",
+                self.operation,
+                self.describe()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// One line summary, e.g. `Allow Write on Topic 'orders.' (Prefixed) for User:alice from *`
+    pub fn describe(&self) -> String {
+        format!(
+            "{:?} {:?} on {:?} '{}' ({:?}) for {} from {}",
+            self.permission,
+            self.operation,
+            self.resource_type,
+            self.resource_name,
+            self.pattern_type,
+            self.principal.to_wire(),
+            self.host
+        )
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Documented, DocumentedFields, FieldTypes)]
+#[serde(deny_unknown_fields)]
+/// A set of ACL bindings managed together, such as everything one application needs.
+/// Plans create the bindings added to the set and delete, by exact match, those removed from it.
+pub struct KafkaAclSet {
+    /// The bindings in the set
+    pub bindings: Vec<KafkaAcl>,
+}
+
+impl KafkaAclSet {
+    /// Parse an ACL file, which may also hold a single `KafkaAcl` binding
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        match RON.from_str::<Self>(s) {
+            Ok(set) => Ok(set),
+            Err(e) => match RON.from_str::<KafkaAcl>(s) {
+                Ok(acl) => Ok(Self { bindings: vec![acl] }),
+                Err(_) => Err(e.into()),
+            },
+        }
+    }
+
+    pub fn binding_set(&self) -> BTreeSet<KafkaAcl> {
+        self.bindings.iter().cloned().collect()
+    }
+}
+
+/// An error diagnostic on the first line of `s` containing `needle`, or on the first line
+fn error_at(s: &str, needle: &str, message: String) -> Diagnostic {
+    let (line, col, len) = s
+        .lines()
+        .enumerate()
+        .find_map(|(i, line)| line.find(needle).map(|col| (i, col, line.len())))
+        .unwrap_or((0, 0, s.lines().next().map(str::len).unwrap_or(0)));
+
+    Diagnostic {
+        severity: DiagnosticSeverity::ERROR as u8,
+        span: DiagnosticSpan {
+            start: DiagnosticPosition {
+                line: line as u32 + 1,
+                col: col as u32 + 1,
+            },
+            end: DiagnosticPosition {
+                line: line as u32 + 1,
+                col: len as u32 + 1,
+            },
+        },
+        message,
+    }
+}
+
+/// Errors for bindings Kafka can't create, placed on the binding's principal name
+fn binding_diagnostics<'a>(s: &str, bindings: impl Iterator<Item = &'a KafkaAcl>) -> Vec<Diagnostic> {
+    bindings
+        .filter_map(|acl| {
+            let e = acl.validate().err()?;
+            Some(error_at(s, &acl.principal.name, format!("{:#}", e)))
+        })
+        .collect()
+}
+
+impl KafkaAclSet {
+    pub fn diag_errors(&self, s: &str) -> Vec<Diagnostic> {
+        binding_diagnostics(s, self.bindings.iter())
+    }
+}
+
+/// Resource name Kafka uses for the cluster in ACL bindings
+pub const CLUSTER_RESOURCE_NAME: &str = "kafka-cluster";
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Documented, DocumentedFields)]
+/// A resource name, matched exactly or as a prefix
+pub enum KafkaResourceMatch {
+    /// The resource with exactly this name; `*` matches every resource
+    Literal(String),
+    /// Every resource whose name starts with this prefix
+    Prefixed(String),
+}
+
+impl KafkaResourceMatch {
+    fn binding(&self, resource_type: KafkaResourceType) -> (KafkaResourceType, String, KafkaResourcePatternType) {
+        match self {
+            KafkaResourceMatch::Literal(name) => (resource_type, name.clone(), KafkaResourcePatternType::Literal),
+            KafkaResourceMatch::Prefixed(prefix) => (resource_type, prefix.clone(), KafkaResourcePatternType::Prefixed),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Documented, DocumentedFields)]
+#[serde(deny_unknown_fields)]
+/// Topics a principal consumes as a consumer group
+pub struct KafkaConsumeAccess {
+    /// The topics read
+    pub topic: KafkaResourceMatch,
+    /// The consumer group the topics are read as
+    pub group: KafkaResourceMatch,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Documented, DocumentedFields, FieldTypes)]
+#[serde(default, deny_unknown_fields)]
+/// What one principal may do in a cluster, expanded into the ACL bindings that allow it.
+/// The file manages every ACL bound to the principal in the cluster: bindings it doesn't
+/// expand to are deleted.
+pub struct KafkaAccess {
+    /// The host the principal connects from (* for all hosts)
+    pub host: String,
+    /// Topics the principal produces to. Grants Write and Describe on the topics, and
+    /// IdempotentWrite on the cluster
+    pub produces: Vec<KafkaResourceMatch>,
+    /// Topics the principal consumes. Grants Read and Describe on the topics, and Read on the group
+    pub consumes: Vec<KafkaConsumeAccess>,
+    /// Transactional IDs the principal produces with. Grants Write and Describe on the IDs
+    pub transactional_ids: Vec<KafkaResourceMatch>,
+    /// Any other bindings for the principal, in full. The principal and host in each must
+    /// match the file's.
+    pub additional_acls: Vec<KafkaAcl>,
+}
+
+impl Default for KafkaAccess {
+    fn default() -> Self {
+        Self {
+            host: String::from("*"),
+            produces: Vec::new(),
+            consumes: Vec::new(),
+            transactional_ids: Vec::new(),
+            additional_acls: Vec::new(),
+        }
+    }
+}
+
+impl KafkaAccess {
+    fn allow(
+        &self,
+        principal: &KafkaPrincipal,
+        (resource_type, resource_name, pattern_type): (KafkaResourceType, String, KafkaResourcePatternType),
+        operations: &[KafkaAclOperation],
+    ) -> Vec<KafkaAcl> {
+        operations
+            .iter()
+            .map(|operation| KafkaAcl {
+                resource_type: resource_type.clone(),
+                resource_name: resource_name.clone(),
+                pattern_type: pattern_type.clone(),
+                principal: principal.clone(),
+                host: self.host.clone(),
+                operation: operation.clone(),
+                permission: KafkaAclPermission::Allow,
+            })
+            .collect()
+    }
+
+    fn produce_bindings(&self, principal: &KafkaPrincipal, topic: &KafkaResourceMatch) -> Vec<KafkaAcl> {
+        let mut bindings = self.allow(
+            principal,
+            topic.binding(KafkaResourceType::Topic),
+            &[KafkaAclOperation::Write, KafkaAclOperation::Describe],
+        );
+        bindings.extend(self.allow(
+            principal,
+            KafkaResourceMatch::Literal(CLUSTER_RESOURCE_NAME.to_string()).binding(KafkaResourceType::Cluster),
+            &[KafkaAclOperation::IdempotentWrite],
+        ));
+        bindings
+    }
+
+    fn consume_bindings(&self, principal: &KafkaPrincipal, consume: &KafkaConsumeAccess) -> Vec<KafkaAcl> {
+        let mut bindings = self.allow(
+            principal,
+            consume.topic.binding(KafkaResourceType::Topic),
+            &[KafkaAclOperation::Read, KafkaAclOperation::Describe],
+        );
+        bindings.extend(self.allow(
+            principal,
+            consume.group.binding(KafkaResourceType::Group),
+            &[KafkaAclOperation::Read],
+        ));
+        bindings
+    }
+
+    fn transactional_id_bindings(&self, principal: &KafkaPrincipal, id: &KafkaResourceMatch) -> Vec<KafkaAcl> {
+        self.allow(
+            principal,
+            id.binding(KafkaResourceType::TransactionalId),
+            &[KafkaAclOperation::Write, KafkaAclOperation::Describe],
+        )
+    }
+
+    /// Errors for an access file at `kafka/{cluster}/access/{file_stem}.ron`
+    pub fn diag_errors(&self, file_stem: &str, s: &str) -> Vec<Diagnostic> {
+        let principal = match KafkaPrincipal::from_file_stem(file_stem) {
+            Ok(principal) => principal,
+            Err(e) => return vec![error_at(s, "", format!("Access file name: {:#}", e))],
+        };
+
+        let mut diagnostics = binding_diagnostics(s, self.additional_acls.iter());
+        for acl in &self.additional_acls {
+            if acl.principal != principal {
+                diagnostics.push(error_at(
+                    s,
+                    &acl.principal.name,
+                    format!(
+                        "Binding is for {}, not this file's {}",
+                        acl.principal.to_wire(),
+                        principal.to_wire()
+                    ),
+                ));
+            }
+            if acl.host != self.host {
+                diagnostics.push(error_at(
+                    s,
+                    &acl.host,
+                    format!("Binding is for host {}, not this file's {}", acl.host, self.host),
+                ));
+            }
+        }
+        diagnostics
+    }
+
+    /// The exact set of ACL bindings this access expands to
+    pub fn bindings(&self, principal: &KafkaPrincipal) -> BTreeSet<KafkaAcl> {
+        let mut bindings = BTreeSet::new();
+
+        for topic in &self.produces {
+            bindings.extend(self.produce_bindings(principal, topic));
+        }
+        for consume in &self.consumes {
+            bindings.extend(self.consume_bindings(principal, consume));
+        }
+        for id in &self.transactional_ids {
+            bindings.extend(self.transactional_id_bindings(principal, id));
+        }
+        bindings.extend(self.additional_acls.iter().cloned());
+
+        bindings
+    }
+
+    /// Describe a principal's live bindings, in terms of the entries of `hint` (normally the
+    /// access file in the repository) that are fully in place. Bindings no entry accounts for
+    /// are listed in `additional_acls`.
+    pub fn from_bindings(principal: &KafkaPrincipal, live: &BTreeSet<KafkaAcl>, hint: Option<&KafkaAccess>) -> Self {
+        let host = match hint {
+            Some(hint) => hint.host.clone(),
+            None => {
+                let hosts: BTreeSet<&str> = live.iter().map(|acl| acl.host.as_str()).collect();
+                match hosts.into_iter().collect::<Vec<_>>()[..] {
+                    [host] => host.to_string(),
+                    _ => String::from("*"),
+                }
+            }
+        };
+
+        let mut access = KafkaAccess {
+            host,
+            ..Default::default()
+        };
+        let present = |bindings: Vec<KafkaAcl>| bindings.iter().all(|acl| live.contains(acl));
+
+        if let Some(hint) = hint {
+            access.produces = hint
+                .produces
+                .iter()
+                .filter(|topic| present(access.produce_bindings(principal, topic)))
+                .cloned()
+                .collect();
+            access.consumes = hint
+                .consumes
+                .iter()
+                .filter(|consume| present(access.consume_bindings(principal, consume)))
+                .cloned()
+                .collect();
+            access.transactional_ids = hint
+                .transactional_ids
+                .iter()
+                .filter(|id| present(access.transactional_id_bindings(principal, id)))
+                .cloned()
+                .collect();
+        }
+
+        let covered = access.bindings(principal);
+        access.additional_acls = live.difference(&covered).cloned().collect();
+        access
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Documented, DocumentedFields)]
 /// The entity type for Kafka quotas
 pub enum KafkaQuotaEntityType {
@@ -166,13 +964,32 @@ pub enum KafkaQuotaEntityType {
 pub struct KafkaQuotaEntity {
     /// The type of entity
     pub entity_type: KafkaQuotaEntityType,
-    /// The name of the entity (user name, client ID, or IP address)
-    pub name: String,
+    /// The name of the entity (user name, client ID, or IP address), or None for the
+    /// default entity of the type, which Kafka writes as `<default>`
+    #[serde(default, deserialize_with = "bare_or_some")]
+    pub name: Option<String>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Documented, DocumentedFields, FieldTypes)]
+impl KafkaQuotaEntity {
+    /// e.g. `user 'alice'` or `default client-id`
+    pub fn describe(&self) -> String {
+        let entity_type = match self.entity_type {
+            KafkaQuotaEntityType::User => "user",
+            KafkaQuotaEntityType::ClientId => "client-id",
+            KafkaQuotaEntityType::Ip => "ip",
+        };
+
+        match &self.name {
+            Some(name) => format!("{} '{}'", entity_type, name),
+            None => format!("default {}", entity_type),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Documented, DocumentedFields, FieldTypes)]
 #[serde(deny_unknown_fields)]
-/// Kafka quotas for rate limiting clients
+/// Kafka quotas for rate limiting clients. The entities may be a user, a client ID, a user
+/// and client ID together, or an IP address, each either named or the default.
 pub struct KafkaQuota {
     /// The entities this quota applies to (e.g., user, client ID)
     pub entities: Vec<KafkaQuotaEntity>,
@@ -182,22 +999,147 @@ pub struct KafkaQuota {
     pub consumer_byte_rate: Option<f64>,
     /// Request percentage quota (percentage, optional)
     pub request_percentage: Option<f64>,
+    /// Rate of partition creations and deletions, and partition additions (partitions/second, optional)
+    pub controller_mutation_rate: Option<f64>,
+    /// Rate of new connections, for IP entities only (connections/second, optional)
+    pub connection_creation_rate: Option<f64>,
+    /// Quota keys not covered above, by their Kafka name
+    #[serde(default)]
+    pub additional_quotas: BTreeMap<String, f64>,
 }
 
-impl Default for KafkaQuota {
-    fn default() -> Self {
-        Self {
-            entities: Vec::new(),
-            producer_byte_rate: None,
-            consumer_byte_rate: None,
-            request_percentage: None,
+impl KafkaQuota {
+    /// Every quota that is set, by its Kafka name
+    pub fn values(&self) -> BTreeMap<String, f64> {
+        let mut values = self.additional_quotas.clone();
+
+        for (key, value) in [
+            ("producer_byte_rate", self.producer_byte_rate),
+            ("consumer_byte_rate", self.consumer_byte_rate),
+            ("request_percentage", self.request_percentage),
+            ("controller_mutation_rate", self.controller_mutation_rate),
+            ("connection_creation_rate", self.connection_creation_rate),
+        ] {
+            if let Some(value) = value {
+                values.insert(key.to_string(), value);
+            }
         }
+
+        values
+    }
+
+    /// Problems Kafka would reject the quota for, each with the setting it concerns
+    pub fn validation_errors(&self) -> Vec<(String, String)> {
+        let mut errors = Vec::new();
+
+        let types: Vec<&KafkaQuotaEntityType> = self.entities.iter().map(|e| &e.entity_type).collect();
+        let is_ip = match types[..] {
+            [KafkaQuotaEntityType::User] | [KafkaQuotaEntityType::ClientId] => false,
+            [KafkaQuotaEntityType::User, KafkaQuotaEntityType::ClientId]
+            | [KafkaQuotaEntityType::ClientId, KafkaQuotaEntityType::User] => false,
+            [KafkaQuotaEntityType::Ip] => true,
+            [] => {
+                errors.push((String::from("entities"), String::from("A quota needs at least one entity")));
+                false
+            }
+            _ => {
+                errors.push((
+                    String::from("entities"),
+                    String::from(
+                        "Kafka accepts quotas for a user, a client ID, a user and client ID together, or an IP address",
+                    ),
+                ));
+                types.contains(&&KafkaQuotaEntityType::Ip)
+            }
+        };
+
+        for entity in &self.entities {
+            if let (KafkaQuotaEntityType::Ip, Some(name)) = (&entity.entity_type, &entity.name)
+                && name.parse::<std::net::IpAddr>().is_err()
+            {
+                errors.push((String::from("entities"), format!("'{}' is not an IP address", name)));
+            }
+            if entity.name.as_deref() == Some("<default>") {
+                errors.push((
+                    String::from("<default>"),
+                    String::from("Use name: None for a default entity rather than '<default>'"),
+                ));
+            }
+        }
+
+        let client_quotas = [
+            ("producer_byte_rate", self.producer_byte_rate),
+            ("consumer_byte_rate", self.consumer_byte_rate),
+            ("request_percentage", self.request_percentage),
+            ("controller_mutation_rate", self.controller_mutation_rate),
+        ];
+        if is_ip {
+            for (key, value) in client_quotas {
+                if value.is_some() {
+                    errors.push((key.to_string(), format!("{} doesn't apply to IP entities", key)));
+                }
+            }
+        } else if self.connection_creation_rate.is_some() {
+            errors.push((
+                String::from("connection_creation_rate"),
+                String::from("connection_creation_rate only applies to IP entities"),
+            ));
+        }
+
+        for (key, value) in self.values() {
+            if value.is_nan() || value < 0.0 {
+                errors.push((key.clone(), format!("{} must be zero or more, not {}", key, value)));
+            }
+        }
+
+        for key in self.additional_quotas.keys() {
+            if matches!(
+                key.as_str(),
+                "producer_byte_rate"
+                    | "consumer_byte_rate"
+                    | "request_percentage"
+                    | "controller_mutation_rate"
+                    | "connection_creation_rate"
+            ) {
+                errors.push((String::from("additional_quotas"), format!("Set {} with its own field", key)));
+            }
+        }
+
+        errors
+    }
+
+    /// e.g. `user 'alice' and default client-id`
+    pub fn describe_entities(&self) -> String {
+        let entities: Vec<String> = self.entities.iter().map(KafkaQuotaEntity::describe).collect();
+        entities.join(" and ")
+    }
+
+    /// Fail with every reason Kafka would reject this quota
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let errors = self.validation_errors();
+
+        if !errors.is_empty() {
+            let messages: Vec<String> = errors.into_iter().map(|(_, message)| message).collect();
+            bail!("Invalid quota for {}: {}", self.describe_entities(), messages.join("; "));
+        }
+
+        Ok(())
+    }
+
+    /// Validation errors as diagnostics, placed on the setting each concerns
+    pub fn diag_errors(&self, s: &str) -> Vec<Diagnostic> {
+        self.validation_errors()
+            .into_iter()
+            .map(|(needle, message)| error_at(s, &needle, message))
+            .collect()
     }
 }
 
 pub enum KafkaResource {
-    Topic(KafkaTopic),
-    Acl(KafkaAcl),
+    Topic(KafkaTopicSpec),
+    SharedTopic(KafkaSharedTopic),
+    Acl(KafkaAclSet),
+    Access(KafkaAccess),
     Quota(KafkaQuota),
 }
 
@@ -206,7 +1148,9 @@ impl Resource for KafkaResource {
         let pretty_config = autoschematic_core::util::PrettyConfig::default().struct_names(true);
         match self {
             KafkaResource::Topic(topic) => Ok(RON.to_string_pretty(&topic, pretty_config)?.into()),
+            KafkaResource::SharedTopic(topic) => Ok(RON.to_string_pretty(&topic, pretty_config)?.into()),
             KafkaResource::Acl(acl) => Ok(RON.to_string_pretty(&acl, pretty_config)?.into()),
+            KafkaResource::Access(access) => Ok(RON.to_string_pretty(&access, pretty_config)?.into()),
             KafkaResource::Quota(quota) => Ok(RON.to_string_pretty(&quota, pretty_config)?.into()),
         }
     }
@@ -220,9 +1164,91 @@ impl Resource for KafkaResource {
 
         match addr {
             KafkaResourceAddress::Topic { .. } => Ok(KafkaResource::Topic(RON.from_str(s)?)),
-            KafkaResourceAddress::Acl { .. } => Ok(KafkaResource::Acl(RON.from_str(s)?)),
+            KafkaResourceAddress::SharedTopic { .. } => Ok(KafkaResource::SharedTopic(RON.from_str(s)?)),
+            KafkaResourceAddress::Acl { .. } => Ok(KafkaResource::Acl(KafkaAclSet::parse(s)?)),
+            KafkaResourceAddress::Access { .. } => Ok(KafkaResource::Access(RON.from_str(s)?)),
             KafkaResourceAddress::Quota { .. } => Ok(KafkaResource::Quota(RON.from_str(s)?)),
             _ => Err(invalid_addr(&addr)),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_baseline_topic_file() {
+        let spec: KafkaTopicSpec = RON
+            .from_str(r#"KafkaTopic(partitions: 3, replication_factor: 2, config: {"cleanup.policy": "compact"})"#)
+            .unwrap();
+
+        assert_eq!(spec.partitions, Some(3));
+        assert_eq!(spec.replication_factor, Some(2));
+
+        let topic = spec.resolve(None);
+        assert_eq!(topic.partitions, 3);
+        assert_eq!(topic.replication_factor, 2);
+    }
+
+    #[test]
+    fn parses_optional_topic_settings() {
+        let spec: KafkaTopicSpec = RON
+            .from_str(r#"KafkaTopic(profile: Some("events"), partitions: Some(6))"#)
+            .unwrap();
+        assert_eq!(spec.partitions, Some(6));
+        assert_eq!(spec.replication_factor, None);
+
+        let spec: KafkaTopicSpec = RON.from_str("KafkaTopic(partitions: None)").unwrap();
+        assert_eq!(spec.partitions, None);
+    }
+
+    #[test]
+    fn parses_baseline_quota_file() {
+        let quota: KafkaQuota = RON
+            .from_str(
+                r#"KafkaQuota(
+                    entities: [KafkaQuotaEntity(entity_type: User, name: "alice")],
+                    producer_byte_rate: Some(1048576.0),
+                    consumer_byte_rate: None,
+                    request_percentage: None,
+                )"#,
+            )
+            .unwrap();
+
+        assert_eq!(quota.entities[0].name.as_deref(), Some("alice"));
+        assert_eq!(quota.producer_byte_rate, Some(1048576.0));
+        assert_eq!(quota.controller_mutation_rate, None);
+    }
+
+    #[test]
+    fn parses_default_quota_entity() {
+        let entity: KafkaQuotaEntity = RON.from_str("KafkaQuotaEntity(entity_type: ClientId, name: None)").unwrap();
+        assert_eq!(entity.name, None);
+
+        let entity: KafkaQuotaEntity = RON.from_str("KafkaQuotaEntity(entity_type: ClientId)").unwrap();
+        assert_eq!(entity.name, None);
+    }
+
+    #[test]
+    fn access_rejects_additional_acls_for_other_hosts() {
+        let acl = KafkaAcl {
+            resource_name: String::from("orders"),
+            principal: KafkaPrincipal::user("alice"),
+            operation: KafkaAclOperation::Read,
+            ..Default::default()
+        };
+        let mut access = KafkaAccess {
+            host: String::from("10.0.0.1"),
+            additional_acls: vec![acl],
+            ..Default::default()
+        };
+
+        let diagnostics = access.diag_errors("alice", "host: \"*\"");
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].message.contains("host *"));
+
+        access.additional_acls[0].host = String::from("10.0.0.1");
+        assert!(access.diag_errors("alice", "").is_empty());
+    }
+}
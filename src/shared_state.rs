@@ -0,0 +1,41 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{addr::SHARED_DIR, state_file};
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+/// The clusters each shared topic definition has been applied to, recorded at
+/// kafka/_shared/topic_state.ron. The record outlives the cluster's entry in the definition,
+/// so a cluster dropped from it still has its topic deleted, while a same-named topic on a
+/// cluster the definition never listed is left alone.
+pub struct KafkaSharedTopicState {
+    pub topics: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl KafkaSharedTopicState {
+    pub fn path(prefix: &Path) -> PathBuf {
+        prefix.join("kafka").join(SHARED_DIR).join("topic_state.ron")
+    }
+
+    /// The clusters recorded for one shared topic
+    pub fn applied(prefix: &Path, topic: &str) -> anyhow::Result<BTreeSet<String>> {
+        let state: Option<Self> = state_file::read(&Self::path(prefix))?;
+        Ok(state.and_then(|mut state| state.topics.remove(topic)).unwrap_or_default())
+    }
+
+    /// Record that a shared topic definition was applied to a cluster, or deleted from it
+    pub fn set_applied(prefix: &Path, topic: &str, cluster: &str, applied: bool) -> anyhow::Result<()> {
+        state_file::update(&Self::path(prefix), |state: &mut Self| {
+            let changed = if applied {
+                state.topics.entry(topic.to_string()).or_default().insert(cluster.to_string())
+            } else {
+                state.topics.get_mut(topic).is_some_and(|clusters| clusters.remove(cluster))
+            };
+            state.topics.retain(|_, clusters| !clusters.is_empty());
+            changed
+        })
+    }
+}
@@ -0,0 +1,73 @@
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use anyhow::Context;
+use autoschematic_core::util::{PrettyConfig, RON};
+use serde::{Serialize, de::DeserializeOwned};
+
+/// Read one of the connector's state files under a shared lock, or None if it doesn't exist
+pub fn read<T: DeserializeOwned>(path: &Path) -> anyhow::Result<Option<T>> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to open {}", path.display())),
+    };
+
+    file.lock_shared()
+        .with_context(|| format!("Failed to lock {}", path.display()))?;
+
+    let mut s = String::new();
+    file.read_to_string(&mut s)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    Ok(Some(
+        RON.from_str(&s)
+            .with_context(|| format!("Failed to parse {}", path.display()))?,
+    ))
+}
+
+/// Change one of the connector's state files under an exclusive lock, so that concurrent ops
+/// can't lose each other's writes. `change` returns whether it changed the state; the file is
+/// created if it doesn't exist, and otherwise only rewritten if the state changed.
+pub fn update<T: Serialize + DeserializeOwned + Default>(
+    path: &Path,
+    change: impl FnOnce(&mut T) -> bool,
+) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+
+    file.lock().with_context(|| format!("Failed to lock {}", path.display()))?;
+
+    let mut s = String::new();
+    file.read_to_string(&mut s)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let created = s.trim().is_empty();
+    let mut state: T = if created {
+        T::default()
+    } else {
+        RON.from_str(&s)
+            .with_context(|| format!("Failed to parse {}", path.display()))?
+    };
+
+    if !change(&mut state) && !created {
+        return Ok(());
+    }
+
+    let s = RON.to_string_pretty(&state, PrettyConfig::default())?;
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(s.as_bytes())
+        .and_then(|_| file.sync_all())
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
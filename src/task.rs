@@ -1,6 +1,226 @@
+use std::collections::BTreeMap;
 
-#[derive(Debug, Clone)]
+use documented::{Documented, DocumentedFields};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum KafkaTask {
-    
+    /// Copy every record of a topic to local files, at kafka/tasks/archive_topic.ron
+    ArchiveTopic,
+    /// Produce an archived topic back into a cluster, at kafka/tasks/restore_topic.ron
+    RestoreTopic,
+    /// Copy a topic and its records to another cluster, at kafka/tasks/copy_topic.ron
+    CopyTopic,
+    /// Resolve what a principal may do in a cluster, at kafka/tasks/effective_access.ron
+    EffectiveAccess,
+    /// Find ACL bindings and quotas that outlived what they refer to, at kafka/tasks/audit_orphans.ron
+    AuditOrphans,
+}
+
+impl KafkaTask {
+    pub fn name(&self) -> &'static str {
+        match self {
+            KafkaTask::ArchiveTopic => "archive_topic",
+            KafkaTask::RestoreTopic => "restore_topic",
+            KafkaTask::CopyTopic => "copy_topic",
+            KafkaTask::EffectiveAccess => "effective_access",
+            KafkaTask::AuditOrphans => "audit_orphans",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "archive_topic" => Some(KafkaTask::ArchiveTopic),
+            "restore_topic" => Some(KafkaTask::RestoreTopic),
+            "copy_topic" => Some(KafkaTask::CopyTopic),
+            "effective_access" => Some(KafkaTask::EffectiveAccess),
+            "audit_orphans" => Some(KafkaTask::AuditOrphans),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Documented, DocumentedFields)]
+#[serde(deny_unknown_fields)]
+/// Archive every record in a topic to a local directory
+pub struct KafkaArchiveTopicTask {
+    /// The cluster containing the topic
+    pub cluster: String,
+    /// The topic to archive
+    pub topic: String,
+    /// Directory to write the archive under, relative to the repository root if not absolute
+    pub archive_dir: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Documented, DocumentedFields)]
+#[serde(deny_unknown_fields)]
+/// Produce an archived topic back into a cluster, creating the topic if it doesn't exist
+pub struct KafkaRestoreTopicTask {
+    /// The cluster to restore into
+    pub cluster: String,
+    /// The topic to restore into; may differ from the archived topic's name
+    pub topic: String,
+    /// Path to a single archive, i.e. the directory containing manifest.json
+    pub archive_path: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Documented, DocumentedFields)]
+#[serde(deny_unknown_fields)]
+/// Copy a topic from one configured cluster to another, partition by partition. The target
+/// topic is created with the source's definition if it doesn't exist, and must be empty
+/// otherwise. Records are copied up to the source's high watermarks at the start of the copy.
+pub struct KafkaCopyTopicTask {
+    /// The cluster to copy from
+    pub source_cluster: String,
+    /// The cluster to copy to
+    pub target_cluster: String,
+    /// The topic to copy
+    pub topic: String,
+    /// Name of the topic in the target cluster, if different
+    #[serde(default)]
+    pub target_topic: Option<String>,
+    /// Consumer groups whose committed offsets on the source topic are translated and
+    /// committed on the target topic once every record has been copied. Translated offsets
+    /// may point up to one copy batch early, so consumers re-read rather than skip records.
+    #[serde(default)]
+    pub consumer_groups: Vec<String>,
+}
+
+impl KafkaCopyTopicTask {
+    pub fn target_topic(&self) -> &str {
+        self.target_topic.as_deref().unwrap_or(&self.topic)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Documented, DocumentedFields)]
+#[serde(deny_unknown_fields)]
+/// Report what a principal may do to each topic, consumer group and the cluster, evaluating
+/// every ACL binding in the cluster the way the broker does: deny bindings win over allow
+/// bindings, and anything no binding allows is denied. Super users and
+/// `allow.everyone.if.no.acl.found` are broker settings the report can't see.
+pub struct KafkaEffectiveAccessTask {
+    /// The cluster to evaluate
+    pub cluster: String,
+    /// The principal to resolve, e.g. `User:alice`
+    pub principal: String,
+    /// The host the principal connects from. Without it, allow bindings limited to a host are
+    /// ignored and deny bindings limited to a host are reported as conditional.
+    #[serde(default)]
+    pub host: Option<String>,
+    /// Topics to report on; every topic in the cluster if empty
+    #[serde(default)]
+    pub topics: Vec<String>,
+    /// Consumer groups to report on; every group in the cluster if empty
+    #[serde(default)]
+    pub groups: Vec<String>,
+    /// Also write the report as RON to this path, relative to the repository root if not absolute
+    #[serde(default)]
+    pub report_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Documented, DocumentedFields)]
+#[serde(deny_unknown_fields)]
+/// List candidate removals in a cluster: ACL bindings with literal patterns naming topics or
+/// consumer groups that don't exist, and quotas for client IDs or IP addresses that no
+/// consumer group member uses. Producers don't appear in group membership, so quota findings
+/// are unverified and only listed, never suggested for deletion. Nothing is changed.
+pub struct KafkaOrphanAuditTask {
+    /// The cluster to audit
+    pub cluster: String,
+    /// Also write the report as RON to this path, relative to the repository root if not absolute
+    #[serde(default)]
+    pub report_path: Option<String>,
+}
+
+/// Progress of a copy, carried between task_exec calls so an interrupted copy resumes
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct KafkaCopyTopicState {
+    pub partitions: BTreeMap<i32, KafkaCopyPartitionState>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct KafkaCopyPartitionState {
+    /// Next source offset to read
+    pub next_offset: i64,
+    /// Source high watermark when the copy started; the copy of this partition ends here
+    pub end_offset: i64,
+    /// Target high watermark after the last completed batch
+    pub target_next_offset: i64,
+    /// Records copied so far
+    pub copied: u64,
+    /// Source and target offsets of the first record of each batch, for translating
+    /// consumer group offsets
+    pub checkpoints: Vec<(i64, i64)>,
 }
 
+impl KafkaCopyPartitionState {
+    pub fn is_done(&self) -> bool {
+        self.next_offset >= self.end_offset
+    }
+
+    /// Map a committed source offset to a target offset at or before the same record
+    pub fn translate_offset(&self, source_offset: i64) -> i64 {
+        if source_offset >= self.next_offset {
+            return self.target_next_offset;
+        }
+
+        self.checkpoints
+            .iter()
+            .rev()
+            .find(|(source, _)| *source <= source_offset)
+            .or(self.checkpoints.first())
+            .map(|(_, target)| *target)
+            .unwrap_or(self.target_next_offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress() -> KafkaCopyPartitionState {
+        KafkaCopyPartitionState {
+            next_offset: 3_000,
+            end_offset: 4_000,
+            target_next_offset: 2_900,
+            copied: 2_900,
+            checkpoints: vec![(100, 0), (1_100, 1_000), (2_100, 2_000)],
+        }
+    }
+
+    #[test]
+    fn translates_to_the_checkpoint_at_or_before_the_offset() {
+        let progress = progress();
+
+        assert_eq!(progress.translate_offset(1_100), 1_000);
+        assert_eq!(progress.translate_offset(1_500), 1_000);
+        assert_eq!(progress.translate_offset(2_999), 2_000);
+    }
+
+    #[test]
+    fn translates_offsets_before_the_first_checkpoint_to_it() {
+        let progress = progress();
+
+        assert_eq!(progress.translate_offset(100), 0);
+        assert_eq!(progress.translate_offset(0), 0);
+    }
+
+    #[test]
+    fn translates_offsets_past_the_copy_to_the_target_end() {
+        let progress = progress();
+
+        assert_eq!(progress.translate_offset(3_000), 2_900);
+        assert_eq!(progress.translate_offset(10_000), 2_900);
+    }
+
+    #[test]
+    fn translates_to_the_target_end_without_checkpoints() {
+        let progress = KafkaCopyPartitionState {
+            next_offset: 500,
+            end_offset: 500,
+            ..Default::default()
+        };
+
+        assert_eq!(progress.translate_offset(200), 0);
+    }
+}
@@ -0,0 +1,151 @@
+use anyhow::{Context, bail};
+
+const DURATION_UNITS: &[(&str, i64)] = &[
+    ("ms", 1),
+    ("s", 1_000),
+    ("m", 60 * 1_000),
+    ("h", 60 * 60 * 1_000),
+    ("d", 24 * 60 * 60 * 1_000),
+    ("w", 7 * 24 * 60 * 60 * 1_000),
+];
+
+const SIZE_UNITS: &[(&str, i64)] = &[
+    ("B", 1),
+    ("KB", 1_000),
+    ("MB", 1_000_000),
+    ("GB", 1_000_000_000),
+    ("TB", 1_000_000_000_000),
+    ("KiB", 1 << 10),
+    ("MiB", 1 << 20),
+    ("GiB", 1 << 30),
+    ("TiB", 1 << 40),
+];
+
+/// Units used when rendering values back to the human form, largest first.
+const DURATION_RENDER_UNITS: &[(&str, i64)] = &[
+    ("d", 24 * 60 * 60 * 1_000),
+    ("h", 60 * 60 * 1_000),
+    ("m", 60 * 1_000),
+    ("s", 1_000),
+];
+
+const SIZE_RENDER_UNITS: &[(&str, i64)] = &[("TiB", 1 << 40), ("GiB", 1 << 30), ("MiB", 1 << 20), ("KiB", 1 << 10)];
+
+/// Topic config keys whose values are durations in milliseconds
+pub fn is_duration_key(key: &str) -> bool {
+    key.ends_with(".ms")
+}
+
+/// Topic config keys whose values are sizes in bytes
+pub fn is_size_key(key: &str) -> bool {
+    key.ends_with(".bytes")
+}
+
+fn parse_with_units(value: &str, units: &[(&str, i64)]) -> anyhow::Result<i64> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '-'))
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let number: i64 = number.parse().with_context(|| format!("Invalid number in '{}'", value))?;
+
+    if unit.is_empty() {
+        return Ok(number);
+    }
+
+    let Some((_, multiplier)) = units.iter().find(|(name, _)| *name == unit.trim()) else {
+        bail!(
+            "Unknown unit '{}' in '{}' (expected one of: {})",
+            unit.trim(),
+            value,
+            units.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", ")
+        );
+    };
+
+    number
+        .checked_mul(*multiplier)
+        .with_context(|| format!("Value '{}' is out of range", value))
+}
+
+/// Parse a duration such as `7d`, `12h` or `604800000` into milliseconds.
+pub fn parse_duration_ms(value: &str) -> anyhow::Result<i64> {
+    parse_with_units(value, DURATION_UNITS)
+}
+
+/// Parse a size such as `1GiB`, `500MB` or `1048576` into bytes.
+pub fn parse_size_bytes(value: &str) -> anyhow::Result<i64> {
+    parse_with_units(value, SIZE_UNITS)
+}
+
+fn render_with_units(value: i64, units: &[(&str, i64)]) -> Option<String> {
+    if value <= 0 {
+        return None;
+    }
+
+    units
+        .iter()
+        .find(|(_, multiplier)| value % multiplier == 0)
+        .map(|(name, multiplier)| format!("{}{}", value / multiplier, name))
+}
+
+/// Render a millisecond duration in the largest unit that divides it exactly, e.g. `7d`.
+/// Returns None if no unit larger than milliseconds fits, or for sentinel values like -1.
+pub fn render_duration_ms(value: i64) -> Option<String> {
+    render_with_units(value, DURATION_RENDER_UNITS)
+}
+
+/// Render a byte size in the largest binary unit that divides it exactly, e.g. `1GiB`.
+pub fn render_size_bytes(value: i64) -> Option<String> {
+    render_with_units(value, SIZE_RENDER_UNITS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_units() {
+        assert_eq!(parse_duration_ms("7d").unwrap(), 604_800_000);
+        assert_eq!(parse_duration_ms("12h").unwrap(), 43_200_000);
+        assert_eq!(parse_duration_ms("250ms").unwrap(), 250);
+        assert_eq!(parse_duration_ms(" 30 s ").unwrap(), 30_000);
+        assert_eq!(parse_size_bytes("1GiB").unwrap(), 1 << 30);
+        assert_eq!(parse_size_bytes("500MB").unwrap(), 500_000_000);
+    }
+
+    #[test]
+    fn parses_plain_numbers_and_sentinels() {
+        assert_eq!(parse_duration_ms("604800000").unwrap(), 604_800_000);
+        assert_eq!(parse_duration_ms("-1").unwrap(), -1);
+        assert_eq!(parse_size_bytes("-1").unwrap(), -1);
+    }
+
+    #[test]
+    fn rejects_bad_values() {
+        assert!(parse_duration_ms("7x").is_err());
+        assert!(parse_duration_ms("d").is_err());
+        assert!(parse_size_bytes("1gib").is_err());
+        assert!(parse_size_bytes("9999999TiB").is_err());
+    }
+
+    #[test]
+    fn renders_in_the_largest_exact_unit() {
+        assert_eq!(render_duration_ms(604_800_000).as_deref(), Some("7d"));
+        assert_eq!(render_duration_ms(90_000).as_deref(), Some("90s"));
+        assert_eq!(render_duration_ms(1_500), None);
+        assert_eq!(render_duration_ms(-1), None);
+        assert_eq!(render_size_bytes(1 << 30).as_deref(), Some("1GiB"));
+        assert_eq!(render_size_bytes(1_000_000), None);
+    }
+
+    #[test]
+    fn round_trips() {
+        for ms in [1_000, 60_000, 90_000, 3_600_000, 604_800_000, 31_536_000_000] {
+            assert_eq!(parse_duration_ms(&render_duration_ms(ms).unwrap()).unwrap(), ms);
+        }
+        for bytes in [1 << 10, 3 << 20, 1 << 30, 5 << 40] {
+            assert_eq!(parse_size_bytes(&render_size_bytes(bytes).unwrap()).unwrap(), bytes);
+        }
+    }
+}
@@ -0,0 +1,35 @@
+# Fake OIDC token endpoint for local OAUTHBEARER testing. Issues unsigned (alg: none) JWTs
+# for any client_credentials request, which the broker's unsecured validator accepts.
+import base64
+import json
+import time
+from http.server import BaseHTTPRequestHandler, HTTPServer
+from urllib.parse import parse_qs
+
+
+def b64(data):
+    return base64.urlsafe_b64encode(json.dumps(data).encode()).rstrip(b"=").decode()
+
+
+class TokenHandler(BaseHTTPRequestHandler):
+    def do_POST(self):
+        body = self.rfile.read(int(self.headers.get("Content-Length", 0))).decode()
+        form = parse_qs(body)
+        client_id = form.get("client_id", ["admin"])[0]
+
+        now = int(time.time())
+        claims = {"sub": client_id, "iat": now, "exp": now + 3600}
+        if "scope" in form:
+            claims["scope"] = form["scope"][0]
+
+        token = f"{b64({'alg': 'none'})}.{b64(claims)}."
+        response = json.dumps({"access_token": token, "token_type": "bearer", "expires_in": 3600}).encode()
+
+        self.send_response(200)
+        self.send_header("Content-Type", "application/json")
+        self.send_header("Content-Length", str(len(response)))
+        self.end_headers()
+        self.wfile.write(response)
+
+
+HTTPServer(("0.0.0.0", 8080), TokenHandler).serve_forever()